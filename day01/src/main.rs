@@ -1,4 +1,3 @@
-use anyhow;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
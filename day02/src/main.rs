@@ -1,104 +1,40 @@
+use day05::{read_program, InstructionSet, MachineError, TuringMachine};
 use itertools::iproduct;
-use std::str::FromStr;
 
-fn read_input(path: &str) -> Result<Vec<usize>, anyhow::Error> {
-    let input = std::fs::read_to_string(path)?;
-    let instructions = input
-        .trim()
-        .split(",")
-        .map(|s| usize::from_str(&s).expect("Failed to parse instruction"))
-        .collect();
-    Ok(instructions)
-}
-
-#[derive(PartialEq, Eq)]
-enum Outcome {
-    Success,
-    Halt,
-}
-
-struct TuringMachine {
-    memory_tape: Vec<usize>,
-    instruction_pointer: usize,
-}
-
-impl TuringMachine {
-    fn new(memory_tape: Vec<usize>) -> Self {
-        assert!(
-            memory_tape.len() > 0,
-            "There has to be at least one instruction!"
-        );
-        Self {
-            memory_tape,
-            instruction_pointer: 0,
-        }
-    }
-
-    fn execute(mut self) -> Vec<usize> {
-        loop {
-            let outcome = (&mut self).step();
-            if outcome == Outcome::Halt {
-                break;
-            }
-        }
-        self.memory_tape
-    }
+/// The day 2 program runs in a few dozen steps: any noun/verb pair going beyond
+/// this budget is stuck in a loop.
+const STEP_BUDGET: u64 = 10_000;
 
-    fn step(&mut self) -> Outcome {
-        let opcode = &self.memory_tape[self.instruction_pointer];
-        match opcode {
-            1 => {
-                let lhs_index = self.memory_tape[self.instruction_pointer + 1].clone();
-                let rhs_index = self.memory_tape[self.instruction_pointer + 2].clone();
-                let output_index = self.memory_tape[self.instruction_pointer + 3].clone();
-                let lhs = self.memory_tape[lhs_index].clone();
-                let rhs = self.memory_tape[rhs_index].clone();
-                let output = lhs + rhs;
-                self.memory_tape[output_index] = output;
-                self.instruction_pointer += 4;
-                Outcome::Success
-            }
-            2 => {
-                let lhs_index = &self.memory_tape[self.instruction_pointer + 1].clone();
-                let rhs_index = &self.memory_tape[self.instruction_pointer + 2].clone();
-                let output_index = &self.memory_tape[self.instruction_pointer + 3].clone();
-                let lhs = &self.memory_tape[*lhs_index].clone();
-                let rhs = &self.memory_tape[*rhs_index].clone();
-                let output = lhs * rhs;
-                self.memory_tape[*output_index] = output;
-                self.instruction_pointer += 4;
-                Outcome::Success
-            }
-            99 => Outcome::Halt,
-            _ => panic!("Unknown opcode!"),
-        }
-    }
-}
-
-fn reproduce_1202_program_alarm(memory_tape: Vec<usize>) {
-    let output_tape = run_program(12, 2, memory_tape);
+fn reproduce_1202_program_alarm(memory_tape: Vec<i64>) {
+    let output_tape = run_program(12, 2, memory_tape).expect("The program failed");
     println!("Position 0: {:?}", output_tape[0]);
 }
 
-fn run_program(noun: usize, verb: usize, mut memory_tape: Vec<usize>) -> Vec<usize> {
+fn run_program(noun: i64, verb: i64, mut memory_tape: Vec<i64>) -> Result<Vec<i64>, MachineError> {
     memory_tape[1] = noun;
     memory_tape[2] = verb;
 
-    TuringMachine::new(memory_tape).execute()
+    let program = TuringMachine::new(memory_tape)
+        .with_instruction_set(InstructionSet::Day2)
+        .with_step_budget(STEP_BUDGET);
+    let (memory_tape, _) = program.execute(vec![])?;
+    Ok(memory_tape)
 }
 
-fn find_input_pair(desired_output: usize, memory_tape: Vec<usize>) -> Option<(usize, usize)> {
+fn find_input_pair(desired_output: i64, memory_tape: Vec<i64>) -> Option<(i64, i64)> {
     for (noun, verb) in iproduct!(0..=99, 0..=99) {
-        let output_tape = run_program(noun, verb, memory_tape.clone());
-        if output_tape[0] == desired_output {
-            return Some((noun, verb));
+        // Some pairs turn the program into an invalid or never-ending one: we skip them.
+        if let Ok(output_tape) = run_program(noun, verb, memory_tape.clone()) {
+            if output_tape[0] == desired_output {
+                return Some((noun, verb));
+            }
         }
     }
     None
 }
 
 fn main() -> Result<(), anyhow::Error> {
-    let memory_tape = read_input("input.txt")?;
+    let memory_tape = read_program("input.txt")?;
 
     reproduce_1202_program_alarm(memory_tape.clone());
 
@@ -108,3 +44,27 @@ fn main() -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use day05::{InstructionSet, TuringMachine};
+
+    #[test]
+    fn part_1_examples() {
+        let examples = vec![
+            (vec![1, 0, 0, 0, 99], vec![2, 0, 0, 0, 99]),
+            (vec![2, 3, 0, 3, 99], vec![2, 3, 0, 6, 99]),
+            (vec![2, 4, 4, 5, 99, 0], vec![2, 4, 4, 5, 99, 9801]),
+            (
+                vec![1, 1, 1, 4, 99, 5, 6, 0, 99],
+                vec![30, 1, 1, 4, 2, 5, 6, 0, 99],
+            ),
+        ];
+        for (memory_tape, expected) in examples {
+            let program =
+                TuringMachine::new(memory_tape).with_instruction_set(InstructionSet::Day2);
+            let (memory_tape, _) = program.execute(vec![]).unwrap();
+            assert_eq!(memory_tape, expected);
+        }
+    }
+}
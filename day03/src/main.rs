@@ -73,7 +73,7 @@ fn get_grid_points(wire: &[Movement]) -> HashSet<GridPoint> {
         let step_function = movement.direction.move_unit();
         for _ in 0..movement.distance {
             current_position = step_function(current_position);
-            grid_points.insert(current_position.clone());
+            grid_points.insert(current_position);
         }
     }
     grid_points
@@ -1,5 +1,3 @@
-use ToString;
-
 type Password = u32;
 
 fn get_digits(password: Password) -> Vec<u32> {
@@ -21,7 +19,7 @@ fn is_strictly_valid(password: Password) -> bool {
 }
 
 fn is_monotone_increasing(digits: &[u32]) -> bool {
-    let mut digits = digits.into_iter();
+    let mut digits = digits.iter();
 
     let mut previous_digit = digits.next().unwrap();
     for digit in digits {
@@ -38,7 +36,7 @@ fn has_six_digits(digits: &[u32]) -> bool {
 }
 
 fn has_two_adjacent_digits(digits: &[u32]) -> bool {
-    let mut digits = digits.into_iter();
+    let mut digits = digits.iter();
 
     let mut previous_digit = digits.next().unwrap();
     for digit in digits {
@@ -51,7 +49,7 @@ fn has_two_adjacent_digits(digits: &[u32]) -> bool {
 }
 
 fn has_two_isolated_adjacent_digits(digits: &[u32]) -> bool {
-    let mut digits = digits.into_iter();
+    let mut digits = digits.iter();
 
     let mut current_digit = digits.next().unwrap();
     let mut group_size = 1;
@@ -0,0 +1,99 @@
+//! Compare execution times with and without the instruction cache.
+//!
+//! Run with `cargo bench -p day05`.
+use day05::{read_program, Status, TuringMachine};
+use std::time::{Duration, Instant};
+
+const N_ITERATIONS: u32 = 100;
+
+fn bench(name: &str, f: impl Fn()) -> Duration {
+    // Warm up
+    f();
+    let start = Instant::now();
+    for _ in 0..N_ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed() / N_ITERATIONS;
+    println!("{:<40} {:>10.3?}", name, elapsed);
+    elapsed
+}
+
+fn boost(memory_tape: &[i64], cache: bool) {
+    let machine = TuringMachine::new(memory_tape.to_vec()).with_instruction_cache(cache);
+    machine.execute(vec![2]).unwrap();
+}
+
+/// Try every permutation of phase settings on a chain of five amplifiers.
+/// Every machine runs a handful of instructions before halting.
+fn amplifiers(memory_tape: &[i64], cache: bool) {
+    let mut phases = [0, 1, 2, 3, 4];
+    for _ in 0..120 {
+        let mut signal = 0;
+        for &phase in &phases {
+            let machine = TuringMachine::new(memory_tape.to_vec()).with_instruction_cache(cache);
+            let (_, outputs) = machine.execute(vec![phase, signal]).unwrap();
+            signal = outputs[0];
+        }
+        next_permutation(&mut phases);
+    }
+}
+
+/// Try every permutation of phase settings on five amplifiers wired in a feedback loop.
+/// Machines are kept alive until the signal stops circulating.
+fn feedback_loop(memory_tape: &[i64], cache: bool) {
+    let mut phases = [5, 6, 7, 8, 9];
+    for _ in 0..120 {
+        let mut machines: Vec<TuringMachine> = phases
+            .iter()
+            .map(|&phase| {
+                let mut machine =
+                    TuringMachine::new(memory_tape.to_vec()).with_instruction_cache(cache);
+                machine.push_input(phase);
+                machine
+            })
+            .collect();
+        let mut signal = 0;
+        'feedback: loop {
+            for machine in machines.iter_mut() {
+                machine.push_input(signal);
+                match machine.run().unwrap() {
+                    Status::Output(output) => signal = output,
+                    _ => break 'feedback,
+                }
+            }
+        }
+        next_permutation(&mut phases);
+    }
+}
+
+fn next_permutation(values: &mut [i64]) {
+    let i = match (1..values.len()).rev().find(|&i| values[i - 1] < values[i]) {
+        Some(i) => i,
+        None => return values.reverse(),
+    };
+    let j = (i..values.len()).rev().find(|&j| values[j] > values[i - 1]).unwrap();
+    values.swap(i - 1, j);
+    values[i..].reverse();
+}
+
+fn main() {
+    let boost_program = read_program("../day09/input.txt").expect("Failed to read day 9 input");
+    let amplifier_program =
+        read_program("../day07/input.txt").expect("Failed to read day 7 input");
+
+    for (name, program, workload) in [
+        ("BOOST (day 9)", &boost_program, boost as fn(&[i64], bool)),
+        ("amplifier chain (day 7)", &amplifier_program, amplifiers),
+        ("amplifier feedback loop (day 7)", &amplifier_program, feedback_loop),
+    ]
+    .iter()
+    {
+        let uncached = bench(&format!("{}, no cache", name), || workload(program, false));
+        let cached = bench(&format!("{}, cache", name), || workload(program, true));
+        println!(
+            "{:<40} {:>10.2}x",
+            "speed-up",
+            uncached.as_secs_f64() / cached.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Talk to Intcode programs that speak ASCII over their inputs and outputs.
+use crate::{MachineError, Status, TuringMachine};
+use std::fmt;
+use std::io::{BufRead, Write};
+
+/// What the program produced during a call to `AsciiMachine::run`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AsciiOutput {
+    /// All outputs that are valid ASCII codes, decoded as text.
+    pub text: String,
+    /// All outputs that are not valid ASCII codes, in the order they were produced.
+    pub non_ascii: Vec<i64>,
+}
+
+/// Text that cannot be sent to the program, with its non-ASCII characters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonAsciiText {
+    pub chars: Vec<char>,
+}
+
+impl fmt::Display for NonAsciiText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chars: Vec<String> = self.chars.iter().map(|c| format!("{:?}", c)).collect();
+        write!(
+            f,
+            "Only ASCII text can be sent to the program, found {}",
+            chars.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for NonAsciiText {}
+
+pub struct AsciiMachine {
+    machine: TuringMachine,
+}
+
+impl AsciiMachine {
+    pub fn new(machine: TuringMachine) -> Self {
+        Self { machine }
+    }
+
+    /// Queue the ASCII codes of `text` as inputs.
+    /// Nothing is queued if `text` contains non-ASCII characters.
+    pub fn send(&mut self, text: &str) -> Result<(), NonAsciiText> {
+        let chars: Vec<char> = text.chars().filter(|c| !c.is_ascii()).collect();
+        if !chars.is_empty() {
+            return Err(NonAsciiText { chars });
+        }
+        for byte in text.bytes() {
+            self.machine.push_input(byte as i64);
+        }
+        Ok(())
+    }
+
+    /// Queue `line`, followed by a newline, as inputs.
+    /// Nothing is queued if `line` contains non-ASCII characters.
+    pub fn send_line(&mut self, line: &str) -> Result<(), NonAsciiText> {
+        self.send(line)?;
+        self.send("\n")
+    }
+
+    /// Run the program until it halts or it is waiting for more input,
+    /// returning what it printed in the meantime.
+    pub fn run(&mut self) -> Result<(AsciiOutput, Status), MachineError> {
+        let mut output = AsciiOutput::default();
+        let status = self.machine.run_with(&mut || None, &mut |value: i64| {
+            if (0..128).contains(&value) {
+                output.text.push(value as u8 as char);
+            } else {
+                output.non_ascii.push(value);
+            }
+        })?;
+        Ok((output, status))
+    }
+
+    pub fn machine(&mut self) -> &mut TuringMachine {
+        &mut self.machine
+    }
+
+    pub fn into_inner(self) -> TuringMachine {
+        self.machine
+    }
+}
+
+/// Wire the program to the terminal: its text goes to stdout and every time it asks
+/// for input a line is read from stdin.
+/// Non-ASCII outputs are printed on their own line, between square brackets.
+pub fn interactive(machine: TuringMachine) -> Result<(), anyhow::Error> {
+    let mut machine = AsciiMachine::new(machine);
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    loop {
+        let (output, status) = machine.run()?;
+        write!(stdout, "{}", output.text)?;
+        for value in output.non_ascii {
+            writeln!(stdout, "[{}]", value)?;
+        }
+        stdout.flush()?;
+        if status == Status::Halted {
+            return Ok(());
+        }
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if stdin.lock().read_line(&mut line)? == 0 {
+                // End of input: the program cannot make progress anymore.
+                return Ok(());
+            }
+            if line.is_ascii() {
+                break;
+            }
+            writeln!(stdout, "Only ASCII input is supported, try again.")?;
+        }
+        machine.send_line(line.trim_end_matches(['\r', '\n']))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::{AsciiMachine, AsciiOutput, NonAsciiText};
+    use crate::assembler::assemble;
+    use crate::{Status, TuringMachine};
+
+    #[test]
+    fn text_in_text_out() {
+        let source = "
+                    OUT #72         ; H
+                    OUT #105        ; i
+                    OUT #10         ; \\n
+            loop:   IN char
+                    EQ char, #10, done
+                    JNZ done, #end
+                    OUT char
+                    JZ #0, #loop
+            end:    OUT #1000
+                    HLT
+            char:   data 0
+            done:   data 0
+        ";
+        let machine = TuringMachine::new(assemble(source).unwrap());
+        let mut machine = AsciiMachine::new(machine);
+
+        let (output, status) = machine.run().unwrap();
+        assert_eq!(output.text, "Hi\n");
+        assert_eq!(status, Status::NeedsInput);
+
+        machine.send_line("echo").unwrap();
+        let (output, status) = machine.run().unwrap();
+        assert_eq!(
+            output,
+            AsciiOutput {
+                text: "echo".into(),
+                non_ascii: vec![1000]
+            }
+        );
+        assert_eq!(status, Status::Halted);
+    }
+
+    #[test]
+    fn non_ascii_text_is_rejected() {
+        let mut machine = AsciiMachine::new(TuringMachine::new(vec![99]));
+        let error = machine.send_line("café ☕").unwrap_err();
+        assert_eq!(error, NonAsciiText { chars: vec!['é', '☕'] });
+        assert_eq!(
+            error.to_string(),
+            "Only ASCII text can be sent to the program, found 'é', '☕'"
+        );
+        assert_eq!(machine.machine().snapshot().pending_inputs, vec![]);
+    }
+}
@@ -0,0 +1,340 @@
+//! An assembler for the textual syntax produced by the disassembler.
+//!
+//! ```text
+//! ; Count down from 3 to 1.
+//!         ADD #3, #0, counter
+//! loop:   OUT counter
+//!         ADD counter, #-1, counter
+//!         JNZ counter, #loop
+//!         HLT
+//! counter: data 0
+//! ```
+//!
+//! Every line holds an optional `label:` followed by an optional statement:
+//! either an instruction (mnemonic and comma-separated operands) or a `data`
+//! directive, which emits its comma-separated values as they are.
+//! Operands are in position mode unless they are prefixed by `#` (immediate)
+//! or `@` (relative). Wherever a number is expected a label can be used,
+//! optionally with an offset (`counter+1`). Everything after `;` is a comment.
+//!
+//! Numeric labels are ignored: the listings printed by the disassembler, with the
+//! address of every line (`   12: OUT 224 ; 4,224`), can be assembled again.
+use crate::{Opcode, ParameterMode};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssemblyErrorKind {
+    UnknownMnemonic(String),
+    WrongNumberOfOperands { expected: usize, found: usize },
+    InvalidOperand(String),
+    InvalidLabel(String),
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+    /// Instructions cannot write to an immediate operand.
+    ImmediateOutput,
+}
+
+/// Why a program could not be assembled, with the (1-based) line number that caused it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssemblyError {
+    pub line: usize,
+    pub kind: AssemblyErrorKind,
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: ", self.line)?;
+        match &self.kind {
+            AssemblyErrorKind::UnknownMnemonic(mnemonic) => {
+                write!(f, "unknown mnemonic `{}`", mnemonic)
+            }
+            AssemblyErrorKind::WrongNumberOfOperands { expected, found } => write!(
+                f,
+                "expected {} operands, found {}",
+                expected, found
+            ),
+            AssemblyErrorKind::InvalidOperand(operand) => {
+                write!(f, "invalid operand `{}`", operand)
+            }
+            AssemblyErrorKind::InvalidLabel(label) => write!(f, "invalid label `{}`", label),
+            AssemblyErrorKind::DuplicateLabel(label) => {
+                write!(f, "label `{}` is defined more than once", label)
+            }
+            AssemblyErrorKind::UndefinedLabel(label) => {
+                write!(f, "label `{}` is not defined", label)
+            }
+            AssemblyErrorKind::ImmediateOutput => {
+                write!(f, "the output operand cannot be in immediate mode")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssemblyError {}
+
+/// A number, or a reference to a label that will be resolved once we know the address
+/// of every label in the program.
+#[derive(Clone, Debug)]
+enum Value {
+    Number(i64),
+    Label { name: String, offset: i64 },
+}
+
+impl Value {
+    fn resolve(&self, labels: &HashMap<String, usize>) -> Result<i64, AssemblyErrorKind> {
+        match self {
+            Value::Number(n) => Ok(*n),
+            Value::Label { name, offset } => labels
+                .get(name)
+                .map(|&address| address as i64 + offset)
+                .ok_or_else(|| AssemblyErrorKind::UndefinedLabel(name.clone())),
+        }
+    }
+}
+
+enum Statement {
+    Instruction {
+        opcode: Opcode,
+        operands: Vec<(ParameterMode, Value)>,
+    },
+    Data(Vec<Value>),
+}
+
+impl Statement {
+    fn n_words(&self) -> usize {
+        match self {
+            Statement::Instruction { operands, .. } => 1 + operands.len(),
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+/// Turn assembly source code into an Intcode program.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AssemblyError> {
+    // First pass: parse every line and work out the address of each label.
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |kind| AssemblyError {
+            line: line_number,
+            kind,
+        };
+        let line = line.split(';').next().unwrap().trim();
+        let line = match split_label(line).map_err(error)? {
+            (Some(label), rest) => {
+                if labels.insert(label.to_string(), address).is_some() {
+                    return Err(error(AssemblyErrorKind::DuplicateLabel(label.to_string())));
+                }
+                rest
+            }
+            (None, rest) => rest,
+        };
+        if line.is_empty() {
+            continue;
+        }
+        let statement = parse_statement(line).map_err(error)?;
+        address += statement.n_words();
+        statements.push((line_number, statement));
+    }
+
+    // Second pass: emit the program, now that labels can be resolved.
+    let mut program = Vec::with_capacity(address);
+    for (line_number, statement) in statements {
+        let error = |kind| AssemblyError {
+            line: line_number,
+            kind,
+        };
+        match statement {
+            Statement::Instruction { opcode, operands } => {
+                let mut word = opcode.code() as i64;
+                let mut factor = 100;
+                for (mode, _) in &operands {
+                    word += factor * parameter_mode_digit(*mode);
+                    factor *= 10;
+                }
+                program.push(word);
+                for (_, value) in &operands {
+                    program.push(value.resolve(&labels).map_err(error)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in &values {
+                    program.push(value.resolve(&labels).map_err(error)?);
+                }
+            }
+        }
+    }
+    Ok(program)
+}
+
+fn parameter_mode_digit(mode: ParameterMode) -> i64 {
+    match mode {
+        ParameterMode::Position => 0,
+        ParameterMode::Immediate => 1,
+        ParameterMode::Relative => 2,
+    }
+}
+
+fn split_label(line: &str) -> Result<(Option<&str>, &str), AssemblyErrorKind> {
+    match line.find(':') {
+        Some(index) => {
+            let label = line[..index].trim();
+            if !label.is_empty() && label.bytes().all(|b| b.is_ascii_digit()) {
+                return Ok((None, line[index + 1..].trim()));
+            }
+            if !is_identifier(label) {
+                return Err(AssemblyErrorKind::InvalidLabel(label.to_string()));
+            }
+            Ok((Some(label), line[index + 1..].trim()))
+        }
+        None => Ok((None, line)),
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn parse_statement(line: &str) -> Result<Statement, AssemblyErrorKind> {
+    let (mnemonic, rest) = match line.find(char::is_whitespace) {
+        Some(index) => (&line[..index], line[index..].trim()),
+        None => (line, ""),
+    };
+    let arguments: Vec<&str> = if rest.is_empty() {
+        vec![]
+    } else {
+        rest.split(',').map(|a| a.trim()).collect()
+    };
+
+    if mnemonic.eq_ignore_ascii_case("data") {
+        let values = arguments
+            .into_iter()
+            .map(parse_value)
+            .collect::<Result<_, _>>()?;
+        return Ok(Statement::Data(values));
+    }
+
+    let opcode = Opcode::from_mnemonic(mnemonic)
+        .ok_or_else(|| AssemblyErrorKind::UnknownMnemonic(mnemonic.to_string()))?;
+    if arguments.len() != opcode.n_parameters() {
+        return Err(AssemblyErrorKind::WrongNumberOfOperands {
+            expected: opcode.n_parameters(),
+            found: arguments.len(),
+        });
+    }
+    let operands: Vec<_> = arguments
+        .into_iter()
+        .map(parse_operand)
+        .collect::<Result<_, _>>()?;
+    if let Some(position) = opcode.output_parameter() {
+        if operands[position - 1].0 == ParameterMode::Immediate {
+            return Err(AssemblyErrorKind::ImmediateOutput);
+        }
+    }
+    Ok(Statement::Instruction { opcode, operands })
+}
+
+fn parse_operand(operand: &str) -> Result<(ParameterMode, Value), AssemblyErrorKind> {
+    if let Some(value) = operand.strip_prefix('#') {
+        Ok((ParameterMode::Immediate, parse_value(value)?))
+    } else if let Some(value) = operand.strip_prefix('@') {
+        Ok((ParameterMode::Relative, parse_value(value)?))
+    } else {
+        Ok((ParameterMode::Position, parse_value(operand)?))
+    }
+}
+
+fn parse_value(value: &str) -> Result<Value, AssemblyErrorKind> {
+    let invalid = || AssemblyErrorKind::InvalidOperand(value.to_string());
+    let value = value.trim();
+    if let Ok(n) = i64::from_str(value) {
+        return Ok(Value::Number(n));
+    }
+    // `label`, `label+offset` or `label-offset`
+    let (name, offset) = match value.find(['+', '-']) {
+        Some(index) => {
+            let offset = i64::from_str(value[index..].trim_start_matches('+').trim())
+                .map_err(|_| invalid())?;
+            (value[..index].trim(), offset)
+        }
+        None => (value, 0),
+    };
+    if !is_identifier(name) {
+        return Err(invalid());
+    }
+    Ok(Value::Label {
+        name: name.to_string(),
+        offset,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::{assemble, AssemblyError, AssemblyErrorKind};
+    use crate::disassembler::disassemble;
+    use crate::{read_program, TuringMachine};
+
+    fn round_trip(program: Vec<i64>) {
+        let source: Vec<String> = disassemble(&program)
+            .into_iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(assemble(&source.join("\n")).unwrap(), program);
+    }
+
+    #[test]
+    fn round_trip_diagnostic_program() {
+        round_trip(read_program("input.txt").unwrap());
+    }
+
+    #[test]
+    fn round_trip_amplifier_program() {
+        round_trip(read_program("../day07/input.txt").unwrap());
+    }
+
+    #[test]
+    fn labels_and_directives() {
+        let source = "
+            ; Count down from 3 to 1.
+                    ADD #3, #0, counter
+            loop:   OUT counter
+                    ADD counter, #-1, counter
+                    JNZ counter, #loop
+                    HLT
+            counter: data 0
+        ";
+        let program = assemble(source).unwrap();
+        assert_eq!(
+            program,
+            vec![1101, 3, 0, 14, 4, 14, 1001, 14, -1, 14, 1005, 14, 4, 99, 0]
+        );
+        let (_, outputs) = TuringMachine::new(program).execute(vec![]).unwrap();
+        assert_eq!(outputs, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_line() {
+        let error = assemble("IN 10\nJMP #0").unwrap_err();
+        assert_eq!(
+            error,
+            AssemblyError {
+                line: 2,
+                kind: AssemblyErrorKind::UnknownMnemonic("JMP".into())
+            }
+        );
+        let error = assemble("ADD #1, #2, #3").unwrap_err();
+        assert_eq!(error.kind, AssemblyErrorKind::ImmediateOutput);
+        let error = assemble("JZ #0, #end").unwrap_err();
+        assert_eq!(error.kind, AssemblyErrorKind::UndefinedLabel("end".into()));
+    }
+}
@@ -0,0 +1,13 @@
+//! Run an ASCII-speaking Intcode program in the terminal.
+//!
+//! Usage: `ascii <program>`
+use day05::ascii::interactive;
+use day05::{read_program, TuringMachine};
+
+fn main() -> Result<(), anyhow::Error> {
+    let path = std::env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow::anyhow!("Usage: ascii <program>"))?;
+    let machine = TuringMachine::new(read_program(&path)?);
+    interactive(machine)
+}
@@ -0,0 +1,29 @@
+//! Print the control-flow graph of an Intcode program in the Graphviz DOT language.
+//!
+//! Usage: `cfg <program> [address=value...]`
+//!
+//! Self-modifying code is not visible to the analysis, but it can be patched in by hand:
+//! the day 5 diagnostic program adds its input to the instruction at address 6, hence
+//! `cfg input.txt 6=1105` shows the code run for the thermal radiator controller (input 5).
+//! Render the graph with e.g. `cargo run --bin cfg input.txt 6=1105 | dot -Tsvg > cfg.svg`.
+use anyhow::anyhow;
+use day05::cfg::control_flow_graph;
+use day05::read_program;
+
+fn main() -> Result<(), anyhow::Error> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| "input.txt".to_string());
+    let mut program = read_program(&path)?;
+    for patch in args {
+        let invalid = || anyhow!("Invalid patch `{}`: expected address=value", patch);
+        let mut parts = patch.splitn(2, '=');
+        let address: usize = parts.next().unwrap().parse().map_err(|_| invalid())?;
+        let value: i64 = parts.next().ok_or_else(invalid)?.parse().map_err(|_| invalid())?;
+        let cell = program
+            .get_mut(address)
+            .ok_or_else(|| anyhow!("Address {} is outside of the program", address))?;
+        *cell = value;
+    }
+    print!("{}", control_flow_graph(&program).to_dot());
+    Ok(())
+}
@@ -0,0 +1,281 @@
+//! An interactive debugger for Intcode programs.
+//!
+//! Usage: `debugger <program> [inputs...]`
+//! Type `help` at the prompt to get the list of available commands.
+use day05::disassembler::{decode, Item};
+use day05::watch::{Access, Watchpoint};
+use day05::{read_program, MachineError, Outcome, TuringMachine};
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+/// How many instructions can be undone.
+const UNDO_CAPACITY: usize = 1_000_000;
+
+const HELP: &str = "\
+Commands:
+  s, step [n]              execute the next n instructions (default: 1)
+  c, continue              run until a breakpoint, an input request or the end of the program
+  back [n]                 undo the last n instructions (default: 1)
+  rewind <address>         undo instructions up to the last one that wrote to address
+  b, break <address>       set a breakpoint
+  d, delete <address>      remove a breakpoint
+  breakpoints              list breakpoints
+  watch <address> [end]    stop when the program writes to the cells from address to end
+  rwatch <address> [end]   stop when the program reads from the cells from address to end
+  awatch <address> [end]   stop when the program reads from or writes to the cells
+  unwatch <address>        remove the watchpoints covering address
+  watchpoints              list watchpoints
+  x, examine <address> [n] print n memory cells starting at address (default: 1)
+  w, write <address> <v>   store v at address
+  j, jump <address>        move the instruction pointer to address
+  i, input <v>...          queue inputs for the program
+  l, list [n]              disassemble n instructions from the instruction pointer (default: 5)
+  r, registers             print the instruction pointer and the relative base
+  h, help                  print this message
+  q, quit                  exit the debugger";
+
+struct Debugger {
+    machine: TuringMachine,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    fn new(machine: TuringMachine) -> Self {
+        Self {
+            machine,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Execute a single instruction, reporting what happened.
+    /// It returns `false` if the machine cannot make progress or it hit a watchpoint.
+    fn step(&mut self) -> bool {
+        let progress = match self.machine.step() {
+            Ok(Outcome::Success) => true,
+            Ok(Outcome::Output(output)) => {
+                println!("Output: {}", output);
+                true
+            }
+            Ok(Outcome::NeedsInput) => {
+                println!("The program is waiting for an input.");
+                false
+            }
+            Ok(Outcome::Halt) => {
+                println!("The program has halted.");
+                false
+            }
+            Err(e) => {
+                println!("Error: {}", e);
+                false
+            }
+        };
+        let hits = self.machine.take_watch_hits();
+        for hit in &hits {
+            let access = match hit.access {
+                Access::Read => "read",
+                Access::Write => "wrote",
+            };
+            let instruction = decode(self.machine.memory(), hit.instruction_pointer);
+            println!(
+                "Watchpoint: `{:>5}: {}` {} {} at address {}.",
+                hit.instruction_pointer, instruction, access, hit.value, hit.address
+            );
+        }
+        progress && hits.is_empty()
+    }
+
+    fn continue_(&mut self) {
+        while self.step() {
+            let instruction_pointer = self.machine.instruction_pointer();
+            if self.breakpoints.contains(&instruction_pointer) {
+                println!("Breakpoint at address {}.", instruction_pointer);
+                break;
+            }
+        }
+    }
+
+    fn list(&self, n_instructions: usize) {
+        let memory = self.machine.memory();
+        let mut address = self.machine.instruction_pointer();
+        for _ in 0..n_instructions {
+            if address >= memory.len() {
+                break;
+            }
+            let item = decode(memory, address);
+            let marker = if self.breakpoints.contains(&address) { '*' } else { ' ' };
+            println!("{} {:>5}: {}", marker, address, item);
+            address += item.n_words();
+        }
+    }
+
+    fn current_instruction(&self) -> String {
+        let memory = self.machine.memory();
+        let address = self.machine.instruction_pointer();
+        let item = if address < memory.len() {
+            decode(memory, address)
+        } else {
+            Item::Data(0)
+        };
+        format!("{:>5}: {}", address, item)
+    }
+
+    /// Execute a command, returning `false` if the user wants to quit.
+    fn execute(&mut self, command: &str, arguments: &[i64]) -> Result<bool, String> {
+        let argument = |i: usize| -> Result<i64, String> {
+            arguments
+                .get(i)
+                .copied()
+                .ok_or_else(|| format!("`{}` expects more arguments", command))
+        };
+        let address = |i: usize| -> Result<usize, String> {
+            let value = argument(i)?;
+            if value < 0 {
+                return Err(format!("{} is not a valid address", value));
+            }
+            Ok(value as usize)
+        };
+        match command {
+            "s" | "step" => {
+                let n_steps = arguments.first().copied().unwrap_or(1);
+                for _ in 0..n_steps {
+                    if !self.step() {
+                        break;
+                    }
+                }
+            }
+            "c" | "continue" => self.continue_(),
+            "back" => {
+                let n_steps = arguments.first().copied().unwrap_or(1).max(0) as usize;
+                let undone = self.machine.step_back(n_steps);
+                if undone < n_steps {
+                    println!("Only {} instructions could be undone.", undone);
+                }
+            }
+            "rewind" => match self.machine.rewind_to_last_write(address(0)?) {
+                Some(undone) => println!("Undid {} instructions.", undone),
+                None => return Err("No undoable instruction wrote to that address".into()),
+            },
+            "b" | "break" => {
+                self.breakpoints.insert(address(0)?);
+            }
+            "d" | "delete" => {
+                self.breakpoints.remove(&address(0)?);
+            }
+            "breakpoints" => {
+                for breakpoint in &self.breakpoints {
+                    println!("{}", breakpoint);
+                }
+            }
+            "watch" | "rwatch" | "awatch" => {
+                let start = address(0)?;
+                let end = if arguments.len() > 1 { address(1)? } else { start };
+                let watchpoint = match command {
+                    "watch" => Watchpoint::writes(start..=end),
+                    "rwatch" => Watchpoint::reads(start..=end),
+                    _ => Watchpoint::accesses(start..=end),
+                };
+                self.machine.watchpoints_mut().push(watchpoint.pausing());
+            }
+            "unwatch" => {
+                let cell = address(0)?;
+                self.machine
+                    .watchpoints_mut()
+                    .retain(|w| !w.addresses.contains(&cell));
+            }
+            "watchpoints" => {
+                for watchpoint in self.machine.watchpoints_mut().iter() {
+                    let access = match (watchpoint.reads, watchpoint.writes) {
+                        (true, true) => "reads and writes",
+                        (true, false) => "reads",
+                        _ => "writes",
+                    };
+                    println!(
+                        "{}..={}: {}",
+                        watchpoint.addresses.start(),
+                        watchpoint.addresses.end(),
+                        access
+                    );
+                }
+            }
+            "x" | "examine" => {
+                let start = address(0)?;
+                let n_cells = arguments.get(1).copied().unwrap_or(1).max(0) as usize;
+                for cell in start..start + n_cells {
+                    let value = self.machine.load(cell).map_err(|e| e.to_string())?;
+                    println!("{:>5}: {}", cell, value);
+                }
+            }
+            "w" | "write" => {
+                self.machine
+                    .store(address(0)?, argument(1)?)
+                    .map_err(|e: MachineError| e.to_string())?;
+            }
+            "j" | "jump" => self.machine.set_instruction_pointer(address(0)?),
+            "i" | "input" => {
+                for &input in arguments {
+                    self.machine.push_input(input);
+                }
+            }
+            "l" | "list" => {
+                let n_instructions = arguments.first().copied().unwrap_or(5).max(0) as usize;
+                self.list(n_instructions);
+                return Ok(true);
+            }
+            "r" | "registers" => {
+                println!("Instruction pointer: {}", self.machine.instruction_pointer());
+                println!("Relative base: {}", self.machine.relative_base());
+            }
+            "h" | "help" => {
+                println!("{}", HELP);
+                return Ok(true);
+            }
+            "q" | "quit" => return Ok(false),
+            _ => return Err(format!("Unknown command `{}`", command)),
+        }
+        println!("{}", self.current_instruction());
+        Ok(true)
+    }
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Usage: debugger <program> [inputs...]"))?;
+    let mut machine = TuringMachine::new(read_program(&path)?).with_undo_log(UNDO_CAPACITY);
+    for input in args {
+        machine.push_input(i64::from_str(&input)?);
+    }
+
+    let mut debugger = Debugger::new(machine);
+    println!("{}", debugger.current_instruction());
+    let stdin = std::io::stdin();
+    loop {
+        print!("(debugger) ");
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let arguments: Result<Vec<i64>, _> = words.map(i64::from_str).collect();
+        let arguments = match arguments {
+            Ok(arguments) => arguments,
+            Err(e) => {
+                println!("Invalid argument: {}", e);
+                continue;
+            }
+        };
+        match debugger.execute(command, &arguments) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("{}", e),
+        }
+    }
+    Ok(())
+}
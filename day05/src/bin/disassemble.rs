@@ -0,0 +1,13 @@
+use day05::disassembler::disassemble;
+use day05::read_program;
+
+fn main() -> Result<(), anyhow::Error> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "input.txt".to_string());
+    let program = read_program(&path)?;
+    for line in disassemble(&program) {
+        println!("{}", line);
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Run an Intcode program and report where it spent its time.
+//!
+//! Usage: `profile <program> [inputs]`, where `inputs` are comma-separated integers.
+//! Outputs are printed first, followed by the report.
+use day05::io::WriterSink;
+use day05::{read_program, TuringMachine};
+use std::str::FromStr;
+
+/// How many hot spots and memory cells to list.
+const TOP: usize = 20;
+
+fn main() -> Result<(), anyhow::Error> {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Usage: profile <program> [inputs]"))?;
+    let mut inputs = args
+        .next()
+        .map(|inputs| {
+            inputs
+                .split(',')
+                .map(|s| i64::from_str(s.trim()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?
+        .unwrap_or_default()
+        .into_iter();
+
+    let mut machine = TuringMachine::new(read_program(&path)?).with_profiling();
+    let status = machine.run_with(&mut inputs, &mut WriterSink::stdout())?;
+    println!("Stopped: {:?}\n", status);
+    print!("{}", machine.profile().unwrap().report(machine.memory(), TOP));
+    Ok(())
+}
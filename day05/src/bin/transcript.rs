@@ -0,0 +1,41 @@
+//! Record the inputs and outputs of an Intcode program, or check that it still
+//! behaves as recorded.
+//!
+//! Usage:
+//! - `transcript record <program> <transcript>`: run the program with inputs from stdin,
+//!   printing its outputs, and save what happened to `transcript`;
+//! - `transcript replay <program> <transcript>`: run the program again with the inputs
+//!   saved in `transcript`, reporting the first divergence.
+use day05::io::{ReaderSource, WriterSink};
+use day05::transcript::Transcript;
+use day05::{read_program, TuringMachine};
+
+const USAGE: &str = "Usage: transcript (record|replay) <program> <transcript>";
+
+fn main() -> Result<(), anyhow::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (mode, program, transcript) = match args.as_slice() {
+        [mode, program, transcript] => (mode.as_str(), program, transcript),
+        _ => anyhow::bail!(USAGE),
+    };
+    let machine = TuringMachine::new(read_program(program)?);
+    match mode {
+        "record" => {
+            let mut machine = machine.with_recording();
+            let mut inputs = ReaderSource::stdin();
+            let result = machine.run_with(&mut inputs, &mut WriterSink::stdout());
+            // Save the transcript even if the program failed: that is the run to reproduce.
+            machine.transcript().unwrap().save(transcript)?;
+            if let Some(e) = inputs.error() {
+                anyhow::bail!("Failed to read inputs: {}", e);
+            }
+            println!("Stopped: {:?}", result?);
+        }
+        "replay" => {
+            machine.replay(&Transcript::load(transcript)?)?;
+            println!("The program behaved as recorded.");
+        }
+        _ => anyhow::bail!(USAGE),
+    }
+    Ok(())
+}
@@ -0,0 +1,287 @@
+//! Recover the control-flow graph of an Intcode program without running it.
+//!
+//! The program is explored from address 0, following both branches of every `JNZ` and
+//! `JZ`, and split into basic blocks: straight runs of instructions that are only
+//! entered from their first instruction and only left from their last one.
+//!
+//! The analysis looks at the program as it is loaded: it does not know about code
+//! the program writes at runtime. Jumps to addresses read from memory (position or
+//! relative mode) cannot be resolved and are marked as indirect.
+use crate::disassembler::{decode, Item, Line};
+use crate::{Opcode, ParameterMode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Where a jump goes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    Address(usize),
+    /// The target is read from memory: it is only known at runtime.
+    Indirect,
+    /// The target is outside of the program.
+    Invalid(i64),
+}
+
+/// How control leaves a basic block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// The block ends with `HLT`.
+    Halt,
+    /// The block runs into the start of another block.
+    FallThrough(usize),
+    /// The block ends with a `JNZ` or a `JZ` whose condition is an immediate value
+    /// that always makes it jump.
+    Jump(Target),
+    /// The block ends with a conditional `JNZ` or `JZ`.
+    Branch { taken: Target, not_taken: Target },
+    /// The block runs into a word that is not a valid instruction, or off the end
+    /// of the program.
+    Invalid,
+}
+
+impl Exit {
+    /// The blocks control can flow to.
+    pub fn successors(&self) -> Vec<usize> {
+        let address = |target: &Target| match target {
+            Target::Address(address) => Some(*address),
+            _ => None,
+        };
+        match self {
+            Exit::Halt | Exit::Invalid => vec![],
+            Exit::FallThrough(next) => vec![*next],
+            Exit::Jump(target) => address(target).into_iter().collect(),
+            Exit::Branch { taken, not_taken } => {
+                address(taken).into_iter().chain(address(not_taken)).collect()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub lines: Vec<Line>,
+    pub exit: Exit,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    /// Basic blocks, by start address.
+    pub blocks: BTreeMap<usize, BasicBlock>,
+}
+
+fn resolve(program: &[i64], value: i64) -> Target {
+    if value >= 0 && (value as usize) < program.len() {
+        Target::Address(value as usize)
+    } else {
+        Target::Invalid(value)
+    }
+}
+
+/// `None` if the instruction does not end a basic block.
+fn exit(program: &[i64], address: usize, item: &Item) -> Option<Exit> {
+    let (opcode, operands) = match item {
+        Item::Data(_) => return Some(Exit::Invalid),
+        Item::Instruction { opcode, operands } => (*opcode, operands),
+    };
+    let jumps_if_true = match opcode {
+        Opcode::Halt => return Some(Exit::Halt),
+        Opcode::JumpIfTrue => true,
+        Opcode::JumpIfFalse => false,
+        _ => return None,
+    };
+    let (condition, target) = (operands[0], operands[1]);
+    let taken = match target.mode {
+        ParameterMode::Immediate => resolve(program, target.value),
+        ParameterMode::Position | ParameterMode::Relative => Target::Indirect,
+    };
+    if condition.mode == ParameterMode::Immediate {
+        return if (condition.value != 0) == jumps_if_true {
+            Some(Exit::Jump(taken))
+        } else {
+            // It never jumps.
+            None
+        };
+    }
+    Some(Exit::Branch {
+        taken,
+        not_taken: resolve(program, (address + item.n_words()) as i64),
+    })
+}
+
+/// Build the control-flow graph of the code reachable from address 0.
+pub fn control_flow_graph(program: &[i64]) -> ControlFlowGraph {
+    // Find where blocks start by exploring the reachable instructions.
+    let mut leaders = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+    let mut to_visit = vec![0];
+    while let Some(mut address) = to_visit.pop() {
+        leaders.insert(address);
+        while address < program.len() && visited.insert(address) {
+            let item = decode(program, address);
+            if let Some(exit) = exit(program, address, &item) {
+                for successor in exit.successors() {
+                    if !leaders.contains(&successor) {
+                        to_visit.push(successor);
+                    }
+                }
+                break;
+            }
+            address += item.n_words();
+        }
+    }
+
+    let mut blocks = BTreeMap::new();
+    for &start in &leaders {
+        let mut lines = vec![];
+        let mut address = start;
+        let exit = loop {
+            if address >= program.len() {
+                break Exit::Invalid;
+            }
+            let item = decode(program, address);
+            let n_words = item.n_words();
+            let exit = exit(program, address, &item);
+            lines.push(Line {
+                address,
+                item,
+                words: program[address..address + n_words].to_vec(),
+            });
+            if let Some(exit) = exit {
+                break exit;
+            }
+            address += n_words;
+            if leaders.contains(&address) {
+                break Exit::FallThrough(address);
+            }
+        };
+        blocks.insert(start, BasicBlock { start, lines, exit });
+    }
+    ControlFlowGraph { blocks }
+}
+
+impl ControlFlowGraph {
+    /// Render the graph in the Graphviz DOT language, e.g. for `dot -Tsvg`.
+    ///
+    /// Blocks that halt have a double border, blocks that run into invalid code are red.
+    /// Unresolved jumps point to a dashed `?` node, jumps outside of the program to a red
+    /// node with the target address.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph cfg {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        let mut invalid_targets = BTreeSet::new();
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for line in &block.lines {
+                write!(label, "{}: {}\\l", line.address, line.item).unwrap();
+            }
+            let style = match block.exit {
+                Exit::Halt => ", peripheries=2",
+                Exit::Invalid => ", color=red",
+                _ => "",
+            };
+            writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, style).unwrap();
+
+            let edges = match block.exit {
+                Exit::Halt | Exit::Invalid => vec![],
+                Exit::FallThrough(next) => vec![(Target::Address(next), "")],
+                Exit::Jump(target) => vec![(target, "")],
+                Exit::Branch { taken, not_taken } => {
+                    vec![(taken, "taken"), (not_taken, "not taken")]
+                }
+            };
+            for (target, label) in edges {
+                let from = block.start;
+                let label = format!("label=\"{}\"", label);
+                match target {
+                    Target::Address(to) => {
+                        writeln!(dot, "    b{} -> b{} [{}];", from, to, label).unwrap()
+                    }
+                    Target::Indirect => {
+                        let node = format!("indirect{}", from);
+                        let style = "shape=circle, style=dashed";
+                        writeln!(dot, "    {} [label=\"?\", {}];", node, style).unwrap();
+                        writeln!(dot, "    b{} -> {} [{}, style=dashed];", from, node, label).unwrap();
+                    }
+                    Target::Invalid(value) => {
+                        invalid_targets.insert(value);
+                        let node = format!("\"invalid {}\"", value);
+                        writeln!(dot, "    b{} -> {} [{}, color=red];", from, node, label).unwrap();
+                    }
+                }
+            }
+        }
+        for value in invalid_targets {
+            let style = "shape=circle, color=red";
+            writeln!(dot, "    \"invalid {}\" [label=\"{}\", {}];", value, value, style).unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+    use crate::cfg::{control_flow_graph, Exit, Target};
+    use crate::read_program;
+
+    #[test]
+    fn basic_blocks() {
+        let source = "
+                    IN x
+            loop:   JZ x, #done
+                    ADD x, #-1, x
+                    JZ #1, #done        ; never jumps
+                    JNZ #1, #loop       ; always jumps
+            done:   OUT x
+                    JNZ x, next         ; indirect
+                    JZ x, #-1
+                    HLT
+            x:      data 0
+            next:   data 0
+        ";
+        let cfg = control_flow_graph(&assemble(source).unwrap());
+        let blocks: Vec<_> = cfg
+            .blocks
+            .values()
+            .map(|b| (b.start, b.lines.len(), b.exit))
+            .collect();
+        let expected = vec![
+            (0, 1, Exit::FallThrough(2)),
+            (2, 1, Exit::Branch { taken: Target::Address(15), not_taken: Target::Address(5) }),
+            (5, 3, Exit::Jump(Target::Address(2))),
+            (15, 2, Exit::Branch { taken: Target::Indirect, not_taken: Target::Address(20) }),
+            (20, 1, Exit::Branch { taken: Target::Invalid(-1), not_taken: Target::Address(23) }),
+            (23, 1, Exit::Halt),
+        ];
+        assert_eq!(blocks, expected);
+
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph cfg {\n"));
+        let label = "5: ADD 24, #-1, 24\\l9: JZ #1, #15\\l12: JNZ #1, #2\\l";
+        assert!(dot.contains(&format!("    b5 [label=\"{}\"];\n", label)));
+        assert!(dot.contains("    b2 -> b15 [label=\"taken\"];\n"));
+        assert!(dot.contains("    b15 -> indirect15 [label=\"taken\", style=dashed];\n"));
+        assert!(dot.contains("    b20 -> \"invalid -1\" [label=\"taken\", color=red];\n"));
+        assert!(dot.contains("    b23 [label=\"23: HLT\\l\", peripheries=2];\n"));
+    }
+
+    #[test]
+    fn diagnostic_program() {
+        let mut program = read_program("input.txt").unwrap();
+        // The instruction at address 6 is only valid once the program has added its input.
+        let cfg = control_flow_graph(&program);
+        assert_eq!(cfg.blocks.len(), 1);
+        assert_eq!(cfg.blocks[&0].exit, Exit::Invalid);
+
+        // With input 5, the tests jump to 99999 when they fail, then the program calls a
+        // subroutine through an address stored in memory.
+        program[6] = 1105;
+        let cfg = control_flow_graph(&program);
+        assert_eq!(cfg.blocks[&0].exit, Exit::Jump(Target::Address(238)));
+        let last = cfg.blocks.values().last().unwrap();
+        assert_eq!((last.start, last.exit), (280, Exit::Jump(Target::Indirect)));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{get_digit, parse_opcode, Opcode, ParameterMode};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// A decoded instruction parameter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Operand {
+    pub mode: ParameterMode,
+    pub value: i64,
+}
+
+impl fmt::Display for Operand {
+    /// Position mode parameters are printed as they are, immediate ones
+    /// are prefixed with `#` and relative ones with `@`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            ParameterMode::Position => write!(f, "{}", self.value),
+            ParameterMode::Immediate => write!(f, "#{}", self.value),
+            ParameterMode::Relative => write!(f, "@{}", self.value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Instruction {
+        opcode: Opcode,
+        operands: Vec<Operand>,
+    },
+    /// A word that does not decode to a valid instruction.
+    Data(i64),
+}
+
+impl Item {
+    /// How many words of the program the item spans.
+    pub fn n_words(&self) -> usize {
+        match self {
+            Item::Instruction { operands, .. } => 1 + operands.len(),
+            Item::Data(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Instruction { opcode, operands } => {
+                write!(f, "{}", opcode.mnemonic())?;
+                for (i, operand) in operands.iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    write!(f, "{}{}", separator, operand)?;
+                }
+                Ok(())
+            }
+            Item::Data(value) => write!(f, "DATA {}", value),
+        }
+    }
+}
+
+/// An entry of a disassembled listing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub address: usize,
+    pub item: Item,
+    /// The words of the program the item has been decoded from.
+    pub words: Vec<i64>,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words: Vec<String> = self.words.iter().map(|w| w.to_string()).collect();
+        write!(
+            f,
+            "{:>5}: {:<32} ; {}",
+            self.address,
+            self.item.to_string(),
+            words.join(",")
+        )
+    }
+}
+
+/// Decode the item starting at `address`.
+/// Anything that could not be executed as it is - unknown opcodes, invalid parameter
+/// modes, writes in immediate mode, instructions running past the end of the
+/// program - is decoded as `Item::Data`.
+pub fn decode(program: &[i64], address: usize) -> Item {
+    let word = program[address];
+    let data = Item::Data(word);
+    if word < 0 {
+        return data;
+    }
+    let (code, parameter_modes) = match parse_opcode(word) {
+        Ok(decoded) => decoded,
+        Err(_) => return data,
+    };
+    let opcode = match Opcode::try_from(code) {
+        Ok(opcode) => opcode,
+        Err(_) => return data,
+    };
+    let n_parameters = opcode.n_parameters();
+    // Parameter modes for parameters the instruction does not have must be left empty.
+    if (n_parameters + 3..=5).any(|digit| get_digit(word, digit as u32) != 0)
+        || word >= 100_000
+    {
+        return data;
+    }
+    if let Some(position) = opcode.output_parameter() {
+        if parameter_modes[position - 1] == ParameterMode::Immediate {
+            return data;
+        }
+    }
+    if address + n_parameters >= program.len() {
+        return data;
+    }
+    let operands = (0..n_parameters)
+        .map(|i| Operand {
+            mode: parameter_modes[i],
+            value: program[address + 1 + i],
+        })
+        .collect();
+    Item::Instruction { opcode, operands }
+}
+
+/// Produce a listing of `program`, one line per instruction, by decoding it
+/// sequentially from its first word.
+pub fn disassemble(program: &[i64]) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+    while address < program.len() {
+        let item = decode(program, address);
+        let len = item.n_words();
+        lines.push(Line {
+            address,
+            item,
+            words: program[address..address + len].to_vec(),
+        });
+        address += len;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disassembler::disassemble;
+
+    #[test]
+    fn listing() {
+        let program = vec![1002, 4, 3, 4, 33, 109, -1, 21101, 1, 2, 3, 4, 10099, -7, 10099];
+        let listing: Vec<String> = disassemble(&program)
+            .into_iter()
+            .map(|line| line.item.to_string())
+            .collect();
+        assert_eq!(
+            listing,
+            vec![
+                "MUL 4, #3, 4",
+                "DATA 33",
+                "ARB #-1",
+                "ADD #1, #2, @3",
+                "OUT 10099",
+                "DATA -7",
+                "DATA 10099",
+            ]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use crate::Opcode;
+use std::fmt;
+
+/// Everything that can go wrong while running an Intcode program.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MachineError {
+    /// The instruction at `address` does not correspond to any known opcode.
+    /// Words that do not fit in an `i64` are clamped to `i64::MIN` or `i64::MAX`.
+    UnknownOpcode { opcode: i64, address: usize },
+    /// The instruction at `address` uses a parameter mode we do not know about.
+    InvalidParameterMode { mode: i64, address: usize },
+    /// The instruction at `instruction_pointer` tried to access a negative address.
+    NegativeAddress { address: i64, instruction_pointer: usize },
+    /// The program tried to access a cell beyond the memory limit of the machine.
+    OutOfMemory { address: usize, memory_limit: usize },
+    /// The program wants to read an input but there are none left.
+    InputExhausted { instruction_pointer: usize },
+    /// The program used up its step budget or its time limit after executing `steps`
+    /// instructions. `instruction_pointer` is the next instruction to be executed.
+    BudgetExhausted { steps: u64, instruction_pointer: usize },
+    /// The ADD, MUL or ARB instruction at `address` overflowed, with `Arithmetic::Checked`.
+    Overflow {
+        opcode: Opcode,
+        address: usize,
+        lhs: i128,
+        rhs: i128,
+    },
+    /// The relative address of a parameter of the instruction at `instruction_pointer`
+    /// overflowed, with `Arithmetic::Checked`.
+    AddressOverflow {
+        relative_base: i128,
+        offset: i128,
+        instruction_pointer: usize,
+    },
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MachineError::UnknownOpcode { opcode, address } => {
+                write!(f, "Unknown opcode {} at address {}", opcode, address)
+            }
+            MachineError::InvalidParameterMode { mode, address } => {
+                write!(f, "Invalid parameter mode {} at address {}", mode, address)
+            }
+            MachineError::NegativeAddress {
+                address,
+                instruction_pointer,
+            } => write!(
+                f,
+                "The instruction at address {} tried to access the negative address {}",
+                instruction_pointer, address
+            ),
+            MachineError::OutOfMemory {
+                address,
+                memory_limit,
+            } => write!(
+                f,
+                "Address {} is beyond the memory limit of {} cells",
+                address, memory_limit
+            ),
+            MachineError::InputExhausted {
+                instruction_pointer,
+            } => write!(
+                f,
+                "Ran out of inputs at address {}",
+                instruction_pointer
+            ),
+            MachineError::BudgetExhausted {
+                steps,
+                instruction_pointer,
+            } => write!(
+                f,
+                "Execution budget exhausted after {} steps, at address {}",
+                steps, instruction_pointer
+            ),
+            MachineError::Overflow {
+                opcode,
+                address,
+                lhs,
+                rhs,
+            } => write!(
+                f,
+                "{} {}, {} overflowed at address {}",
+                opcode.mnemonic(),
+                lhs,
+                rhs,
+                address
+            ),
+            MachineError::AddressOverflow {
+                relative_base,
+                offset,
+                instruction_pointer,
+            } => write!(
+                f,
+                "The instruction at address {} overflowed computing the relative address {} + {}",
+                instruction_pointer, relative_base, offset
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MachineError {}
@@ -0,0 +1,68 @@
+//! The plain-text file formats shared by snapshots and transcripts.
+//!
+//! The first line of a file identifies the format and its version, e.g.
+//! `intcode-snapshot 2`: files written by a different version are rejected.
+use std::fmt;
+use std::path::Path;
+use std::str::{FromStr, Lines};
+
+pub(crate) struct Format {
+    /// What the file holds, for error messages.
+    pub(crate) name: &'static str,
+    pub(crate) header: &'static str,
+    pub(crate) version: u32,
+}
+
+impl Format {
+    pub(crate) fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            format: self.name,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn write_header(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", self.header, self.version)
+    }
+
+    /// Check the header of `s`, returning the lines that follow it.
+    pub(crate) fn check_header<'a>(&self, s: &'a str) -> Result<Lines<'a>, ParseError> {
+        let mut lines = s.lines();
+        let header = lines.next().unwrap_or_default();
+        match header.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [header, version] if *header == self.header => {
+                if u32::from_str(version) != Ok(self.version) {
+                    return Err(self.error(format!("unsupported version {}", version)));
+                }
+            }
+            _ => return Err(self.error("missing header")),
+        }
+        Ok(lines)
+    }
+}
+
+/// Why a snapshot or a transcript could not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    format: &'static str,
+    message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid {}: {}", self.format, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub(crate) fn save(value: &impl fmt::Display, path: impl AsRef<Path>) -> std::io::Result<()> {
+    std::fs::write(path, value.to_string())
+}
+
+pub(crate) fn load<T: FromStr<Err = ParseError>>(
+    path: impl AsRef<Path>,
+) -> Result<T, anyhow::Error> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content.parse()?)
+}
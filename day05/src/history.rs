@@ -0,0 +1,187 @@
+//! Step backwards through the execution of a program.
+//!
+//! With an undo log, a `TuringMachine` remembers, for every instruction it executes,
+//! what the instruction changed: the memory cells it overwrote, the instruction pointer,
+//! the relative base and the input it consumed. Undoing an instruction puts all of them
+//! back, including the input, which is queued again to be consumed on the way forward.
+//!
+//! Only the effects of executed instructions are logged: changes made from outside
+//! (e.g. with `store`), profiles and transcripts are not rolled back.
+use crate::{TuringMachine, Word};
+use std::collections::VecDeque;
+
+/// What a single instruction changed.
+struct UndoStep<W> {
+    instruction_pointer: usize,
+    relative_base: W,
+    /// The length of the memory tape before the instruction grew it.
+    memory_len: usize,
+    /// The previous value of every cell the instruction wrote to, in order.
+    writes: Vec<(usize, W)>,
+    input: Option<W>,
+}
+
+pub(crate) struct UndoLog<W> {
+    steps: VecDeque<UndoStep<W>>,
+    capacity: usize,
+    /// The instruction being executed.
+    current: Option<UndoStep<W>>,
+}
+
+impl<W: Word> UndoLog<W> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            steps: VecDeque::new(),
+            capacity,
+            current: None,
+        }
+    }
+
+    pub(crate) fn begin(
+        &mut self,
+        instruction_pointer: usize,
+        relative_base: W,
+        memory_len: usize,
+    ) {
+        self.current = Some(UndoStep {
+            instruction_pointer,
+            relative_base,
+            memory_len,
+            writes: vec![],
+            input: None,
+        });
+    }
+
+    pub(crate) fn record_write(&mut self, address: usize, previous_value: W) {
+        if let Some(step) = &mut self.current {
+            step.writes.push((address, previous_value));
+        }
+    }
+
+    pub(crate) fn record_input(&mut self, input: W) {
+        if let Some(step) = &mut self.current {
+            step.input = Some(input);
+        }
+    }
+
+    /// The instruction has been executed: it can now be undone.
+    pub(crate) fn commit(&mut self) {
+        if let Some(step) = self.current.take() {
+            self.steps.push_back(step);
+            if self.steps.len() > self.capacity {
+                self.steps.pop_front();
+            }
+        }
+    }
+}
+
+impl<W: Word> TuringMachine<W> {
+    /// Log what every instruction changes, to be able to undo the last `capacity`
+    /// instructions with `step_back` and `rewind_to_last_write`.
+    pub fn with_undo_log(mut self, capacity: usize) -> Self {
+        self.undo_log = Some(UndoLog::new(capacity));
+        self
+    }
+
+    /// How many instructions can be undone.
+    pub fn undoable_steps(&self) -> usize {
+        self.undo_log.as_ref().map_or(0, |log| log.steps.len())
+    }
+
+    /// Undo the last `n` instructions, or as many as the undo log allows.
+    /// It returns the number of instructions that have been undone.
+    pub fn step_back(&mut self, n: usize) -> usize {
+        for undone in 0..n {
+            if !self.undo_last_step() {
+                return undone;
+            }
+        }
+        n
+    }
+
+    /// Undo instructions up to and including the last one that wrote to `address`.
+    /// It returns the number of instructions that have been undone, or `None` (leaving
+    /// the machine untouched) if none of the undoable instructions wrote to `address`.
+    pub fn rewind_to_last_write(&mut self, address: usize) -> Option<usize> {
+        let log = self.undo_log.as_ref()?;
+        let position = log
+            .steps
+            .iter()
+            .rposition(|step| step.writes.iter().any(|&(a, _)| a == address))?;
+        Some(self.step_back(log.steps.len() - position))
+    }
+
+    fn undo_last_step(&mut self) -> bool {
+        let step = match self.undo_log.as_mut().and_then(|log| log.steps.pop_back()) {
+            Some(step) => step,
+            None => return false,
+        };
+        for (address, value) in step.writes.into_iter().rev() {
+            self.store(address, value)
+                .expect("Undoing a write cannot exceed the memory limit");
+        }
+        self.memory_tape.truncate(step.memory_len);
+        self.instruction_pointer = step.instruction_pointer;
+        self.relative_base = step.relative_base;
+        if let Some(input) = step.input {
+            self.inputs.push_front(input);
+        }
+        self.steps -= 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_programs::RUNNING_SUM;
+    use crate::{Outcome, TuringMachine};
+
+    #[test]
+    fn step_back_and_forth() {
+        let mut machine = TuringMachine::new(RUNNING_SUM.to_vec()).with_undo_log(100);
+        machine.push_input(3);
+        machine.push_input(4);
+        for _ in 0..8 {
+            machine.step().unwrap();
+        }
+        assert_eq!(machine.load(30).unwrap(), 7);
+        let snapshot = machine.snapshot();
+
+        // Back to the second IN: the input is queued again.
+        assert_eq!(machine.step_back(3), 3);
+        assert_eq!(machine.instruction_pointer(), 2);
+        assert_eq!(machine.load(30).unwrap(), 3);
+        assert_eq!(machine.steps(), 5);
+        assert_eq!(machine.step().unwrap(), Outcome::Success);
+        assert_eq!(machine.step().unwrap(), Outcome::Success);
+        assert_eq!(machine.step().unwrap(), Outcome::Output(7));
+        assert_eq!(machine.snapshot(), snapshot);
+
+        // All the way back to the start, memory included.
+        assert_eq!(machine.step_back(100), 8);
+        assert_eq!(machine.memory(), &RUNNING_SUM[..]);
+        assert_eq!(machine.relative_base(), 0);
+        assert_eq!(machine.snapshot().pending_inputs, vec![3, 4]);
+    }
+
+    #[test]
+    fn rewind_to_the_last_write() {
+        let mut machine = TuringMachine::new(RUNNING_SUM.to_vec()).with_undo_log(4);
+        for input in 1..=3 {
+            machine.push_input(input);
+        }
+        for _ in 0..12 {
+            machine.step().unwrap();
+        }
+        assert_eq!(machine.load(30).unwrap(), 6);
+
+        // Only the last 4 instructions are logged: JNZ, IN, ADD and OUT.
+        assert_eq!(machine.undoable_steps(), 4);
+        assert_eq!(machine.rewind_to_last_write(0), None);
+        assert_eq!(machine.rewind_to_last_write(30), Some(2));
+        assert_eq!(machine.instruction_pointer(), 4);
+        assert_eq!(machine.load(30).unwrap(), 3);
+        assert_eq!(machine.rewind_to_last_write(20), Some(1));
+        assert_eq!(machine.instruction_pointer(), 2);
+    }
+}
@@ -0,0 +1,197 @@
+//! Where a `TuringMachine` gets its inputs from and where its outputs go,
+//! for `TuringMachine::run_with`.
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+use std::sync::mpsc::{Receiver, Sender};
+
+pub trait InputSource<W = i64> {
+    /// The next input for the program, or `None` if there is none available.
+    fn next_input(&mut self) -> Option<W>;
+}
+
+pub trait OutputSink<W = i64> {
+    fn emit(&mut self, output: W);
+}
+
+impl<W, F> InputSource<W> for F
+where
+    F: FnMut() -> Option<W>,
+{
+    fn next_input(&mut self) -> Option<W> {
+        self()
+    }
+}
+
+impl<W> InputSource<W> for VecDeque<W> {
+    fn next_input(&mut self) -> Option<W> {
+        self.pop_front()
+    }
+}
+
+impl<W> InputSource<W> for std::vec::IntoIter<W> {
+    fn next_input(&mut self) -> Option<W> {
+        self.next()
+    }
+}
+
+/// Blocks until a value is received. It runs dry when all senders have been dropped.
+impl<W> InputSource<W> for Receiver<W> {
+    fn next_input(&mut self) -> Option<W> {
+        self.recv().ok()
+    }
+}
+
+/// Reads comma or whitespace separated integers from a reader, e.g. a file or stdin.
+/// It runs dry at the end of the stream or on the first value that is not an integer:
+/// check `error` to tell the two apart.
+pub struct ReaderSource<R> {
+    reader: R,
+    pending: VecDeque<i64>,
+    error: Option<std::io::Error>,
+}
+
+impl<R: BufRead> ReaderSource<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            pending: VecDeque::new(),
+            error: None,
+        }
+    }
+
+    /// What stopped the source, if it was not the end of the stream.
+    pub fn error(&self) -> Option<&std::io::Error> {
+        self.error.as_ref()
+    }
+
+    fn read_line(&mut self) -> Result<bool, std::io::Error> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(false);
+        }
+        for token in line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|t| !t.is_empty())
+        {
+            let value = i64::from_str(token).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("`{}` is not a valid input: {}", token, e),
+                )
+            })?;
+            self.pending.push_back(value);
+        }
+        Ok(true)
+    }
+}
+
+impl ReaderSource<std::io::StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        Self::new(std::io::stdin().lock())
+    }
+}
+
+impl<R: BufRead> InputSource for ReaderSource<R> {
+    fn next_input(&mut self) -> Option<i64> {
+        while self.pending.is_empty() && self.error.is_none() {
+            match self.read_line() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => self.error = Some(e),
+            }
+        }
+        self.pending.pop_front()
+    }
+}
+
+impl<W, F> OutputSink<W> for F
+where
+    F: FnMut(W),
+{
+    fn emit(&mut self, output: W) {
+        self(output)
+    }
+}
+
+impl<W> OutputSink<W> for Vec<W> {
+    fn emit(&mut self, output: W) {
+        self.push(output)
+    }
+}
+
+impl<W> OutputSink<W> for VecDeque<W> {
+    fn emit(&mut self, output: W) {
+        self.push_back(output)
+    }
+}
+
+/// Outputs are dropped if the receiver has hung up.
+impl<W> OutputSink<W> for Sender<W> {
+    fn emit(&mut self, output: W) {
+        let _ = self.send(output);
+    }
+}
+
+/// Writes every output on its own line, e.g. to stdout or to a file.
+pub struct WriterSink<T> {
+    writer: T,
+}
+
+impl<T: Write> WriterSink<T> {
+    pub fn new(writer: T) -> Self {
+        Self { writer }
+    }
+}
+
+impl WriterSink<std::io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+impl<T: Write, W: fmt::Display> OutputSink<W> for WriterSink<T> {
+    fn emit(&mut self, output: W) {
+        writeln!(self.writer, "{}", output).expect("Failed to write output");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::ReaderSource;
+    use crate::test_programs::RUNNING_SUM;
+    use crate::{Status, TuringMachine};
+    use std::io::Cursor;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn inputs_from_a_reader_and_outputs_to_a_closure() {
+        let mut machine = TuringMachine::new(RUNNING_SUM.to_vec());
+        let mut inputs = ReaderSource::new(Cursor::new("1, 2\n3\n\n4"));
+        let mut outputs = Vec::new();
+        let status = machine
+            .run_with(&mut inputs, &mut |output| outputs.push(output))
+            .unwrap();
+        assert_eq!(status, Status::NeedsInput);
+        assert_eq!(outputs, vec![1, 3, 6, 10]);
+        assert!(inputs.error().is_none());
+    }
+
+    #[test]
+    fn inputs_and_outputs_over_channels() {
+        let (input_sender, mut input_receiver) = channel();
+        let (mut output_sender, output_receiver) = channel();
+        for i in 1..=3 {
+            input_sender.send(i).unwrap();
+        }
+        drop(input_sender);
+
+        let mut machine = TuringMachine::new(RUNNING_SUM.to_vec());
+        let status = machine
+            .run_with(&mut input_receiver, &mut output_sender)
+            .unwrap();
+        assert_eq!(status, Status::NeedsInput);
+        assert_eq!(output_receiver.try_iter().collect::<Vec<_>>(), vec![1, 3, 6]);
+    }
+}
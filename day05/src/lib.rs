@@ -1,182 +1,1016 @@
-#[derive(Copy, Clone, Debug)]
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+pub mod ascii;
+pub mod assembler;
+pub mod cfg;
+pub mod disassembler;
+mod error;
+mod format;
+mod history;
+pub mod io;
+pub mod network;
+pub mod nic;
+mod opcode;
+pub mod profile;
+pub mod snapshot;
+mod trace;
+pub mod transcript;
+pub mod watch;
+mod word;
+
+pub use error::MachineError;
+pub use format::ParseError;
+pub use io::{InputSource, OutputSink};
+pub use opcode::{InstructionSet, Opcode};
+pub use profile::Profile;
+pub use num_bigint::BigInt;
+pub use trace::{Event, NoopTracer, PrintTracer, Tracer};
+pub use word::Word;
+
+use history::UndoLog;
+use transcript::{Entry, Transcript};
+use watch::{Access, Hit, Watchpoint};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ParameterMode {
     Position,
     Immediate,
+    Relative,
 }
 
-impl From<i32> for ParameterMode {
-    fn from(p: i32) -> Self {
+impl TryFrom<i64> for ParameterMode {
+    /// The invalid parameter mode.
+    type Error = i64;
+
+    fn try_from(p: i64) -> Result<Self, Self::Error> {
         match p {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            _ => panic!("Invalid parameter mode!")
+            0 => Ok(ParameterMode::Position),
+            1 => Ok(ParameterMode::Immediate),
+            2 => Ok(ParameterMode::Relative),
+            _ => Err(p),
         }
     }
 }
 
-// This would be an awesome spot to leverage const generics!
-fn parse_opcode(d: i32) -> (u32, Vec<ParameterMode>) {
+// It returns the first invalid parameter mode, if there is one.
+fn parse_opcode(d: i64) -> Result<(u32, [ParameterMode; 3]), i64> {
     let opcode = d % 100;
     // We don't have any instruction with more than 3 parameters
-    let parameter_modes = vec![
-        get_digit(d, 3).into(),
-        get_digit(d, 4).into(),
-        get_digit(d, 5).into(),
+    let parameter_modes = [
+        ParameterMode::try_from(d / 100 % 10)?,
+        ParameterMode::try_from(d / 1_000 % 10)?,
+        ParameterMode::try_from(d / 10_000 % 10)?,
     ];
-    (opcode as u32, parameter_modes)
+    Ok((opcode as u32, parameter_modes))
 }
 
-fn get_digit(n: i32, digit_position: u32) -> i32 {
-    (n / 10_i32.pow(digit_position - 1)) % 10
+fn get_digit(n: i64, digit_position: u32) -> i64 {
+    (n / 10_i64.pow(digit_position - 1)) % 10
 }
 
-#[derive(PartialEq, Eq)]
-enum Outcome {
+/// Words that do not fit in an `i64` are clamped to `i64::MIN` or `i64::MAX`,
+/// e.g. to report them in a `MachineError`.
+fn saturating_to_i64<W: Word>(value: &W) -> i64 {
+    value.to_i64().unwrap_or_else(|| {
+        if *value < W::from(0) {
+            i64::MIN
+        } else {
+            i64::MAX
+        }
+    })
+}
+
+/// Read a comma-separated Intcode program from a file.
+pub fn read_program(path: impl AsRef<Path>) -> Result<Vec<i64>, anyhow::Error> {
+    let input = std::fs::read_to_string(path)?;
+    let instructions = input
+        .trim()
+        .split(',')
+        .map(|s| i64::from_str(s.trim()))
+        .collect::<Result<_, _>>()?;
+    Ok(instructions)
+}
+
+/// The result of executing a single instruction with `TuringMachine::step`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome<W = i64> {
+    /// The instruction has been executed and did not produce any output.
     Success,
-    Output(i32),
+    Output(W),
+    /// The instruction wants an input but the input queue is empty: it has not been executed.
+    NeedsInput,
+    /// The instruction pointer is on opcode 99.
     Halt,
 }
 
-pub struct TuringMachine {
-    memory_tape: Vec<i32>,
+/// Why a call to `TuringMachine::run` returned control to the caller.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status<W = i64> {
+    /// The program wants to read an input but the input queue is empty.
+    /// Push more inputs with `push_input` and call `run` again to resume.
+    NeedsInput,
+    /// The program produced an output.
+    Output(W),
+    /// The program reached opcode 99.
+    Halted,
+    /// A pausing watchpoint has been triggered by the last executed instruction.
+    Watchpoint(Hit<W>),
+}
+
+/// What ADD, MUL and ARB, as well as relative parameters, do when the result does not
+/// fit in a word.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Arithmetic {
+    /// Wrap around, two's complement style. It is the default.
+    Wrapping,
+    /// Stop the machine with `MachineError::Overflow`.
+    Checked,
+}
+
+/// How often, in instructions, a machine with a time limit looks at the clock.
+pub const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// A decoded instruction word.
+/// Parameters are not part of it: they are read from memory when the instruction is executed.
+#[derive(Copy, Clone, Debug)]
+struct Instruction {
+    opcode: Opcode,
+    parameter_modes: [ParameterMode; 3],
+}
+
+/// The default cap on the number of memory cells a `TuringMachine` can use (128 MiB of words).
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 24;
+
+/// An Intcode computer.
+///
+/// Memory cells hold `W` words: `i64` by default, use `from_words` to pick a narrower
+/// or wider type if the program needs it.
+pub struct TuringMachine<W = i64> {
+    memory_tape: Vec<W>,
     instruction_pointer: usize,
+    relative_base: W,
+    memory_limit: usize,
+    inputs: VecDeque<W>,
+    tracer: Box<dyn Tracer<W>>,
+    /// The decoded instruction for every address we executed, if it has not been
+    /// overwritten since. `None` if caching is disabled.
+    instruction_cache: Option<Vec<Option<Instruction>>>,
+    instruction_set: InstructionSet,
+    arithmetic: Arithmetic,
+    /// The number of instructions executed so far.
+    steps: u64,
+    step_budget: Option<u64>,
+    time_limit: Option<Duration>,
+    /// When the machine executed its first instruction.
+    started_at: Option<Instant>,
+    /// `None` if profiling is disabled.
+    profile: Option<Profile>,
+    /// `None` if recording is disabled.
+    transcript: Option<Transcript<W>>,
+    /// `None` if reverse execution is disabled.
+    undo_log: Option<UndoLog<W>>,
+    watchpoints: Vec<Watchpoint>,
+    /// The last `WATCH_HITS_CAPACITY` watchpoint hits.
+    watch_hits: VecDeque<Hit<W>>,
+    /// The hits of the instruction being executed, and whether they pause the machine:
+    /// they are only reported if the instruction completes.
+    pending_hits: Vec<(Hit<W>, bool)>,
+    /// The pausing watchpoint hit `run` or `run_with` has yet to report.
+    paused_on: Option<Hit<W>>,
 }
 
 impl TuringMachine {
-    pub fn new(memory_tape: Vec<i32>) -> Self {
+    pub fn new(memory_tape: Vec<i64>) -> Self {
+        Self::from_words(memory_tape)
+    }
+}
+
+impl<W: Word> TuringMachine<W> {
+    /// Build a machine working with `W` words, e.g. `TuringMachine::<i128>::from_words`.
+    pub fn from_words(memory_tape: Vec<W>) -> Self {
         assert!(
-            memory_tape.len() > 0,
+            !memory_tape.is_empty(),
             "The memory tape cannot be empty!"
         );
         Self {
             memory_tape,
             instruction_pointer: 0,
+            relative_base: W::from(0),
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            inputs: VecDeque::new(),
+            tracer: Box::new(NoopTracer),
+            instruction_cache: Some(Vec::new()),
+            instruction_set: InstructionSet::Full,
+            arithmetic: Arithmetic::Wrapping,
+            steps: 0,
+            step_budget: None,
+            time_limit: None,
+            started_at: None,
+            profile: None,
+            transcript: None,
+            undo_log: None,
+            watchpoints: Vec::new(),
+            watch_hits: VecDeque::new(),
+            pending_hits: Vec::new(),
+            paused_on: None,
         }
     }
 
-    pub fn execute(mut self, inputs: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+    /// Stop the program with `MachineError::BudgetExhausted` once it has executed
+    /// `step_budget` instructions.
+    pub fn with_step_budget(mut self, step_budget: u64) -> Self {
+        self.step_budget = Some(step_budget);
+        self
+    }
+
+    /// Stop the program with `MachineError::BudgetExhausted` once `time_limit` has elapsed
+    /// since it executed its first instruction.
+    /// The clock is checked every `TIME_CHECK_INTERVAL` instructions.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Restrict the instructions the program is allowed to use.
+    /// Unsupported opcodes fail with `MachineError::UnknownOpcode`, unsupported parameter
+    /// modes with `MachineError::InvalidParameterMode`.
+    pub fn with_instruction_set(mut self, instruction_set: InstructionSet) -> Self {
+        self.instruction_set = instruction_set;
+        // Cached instructions have been validated against the previous instruction set.
+        if self.instruction_cache.is_some() {
+            self.instruction_cache = Some(Vec::new());
+        }
+        self
+    }
+
+    /// Choose how ADD, MUL and ARB handle overflow: use `Arithmetic::Checked` to find out
+    /// whether a wrong answer comes from an overflow.
+    pub fn with_arithmetic(mut self, arithmetic: Arithmetic) -> Self {
+        self.arithmetic = arithmetic;
+        self
+    }
+
+    /// Decoded instructions are cached by default, to avoid decoding them again
+    /// every time they are executed.
+    /// The cache is only allocated once the program has executed as many instructions
+    /// as its tape has cells: short-lived machines do not pay for it.
+    pub fn with_instruction_cache(mut self, enabled: bool) -> Self {
+        self.instruction_cache = if enabled { Some(Vec::new()) } else { None };
+        self
+    }
+
+    /// Report every instruction, parameter, write, input and output to `tracer`.
+    pub fn with_tracer(mut self, tracer: impl Tracer<W> + 'static) -> Self {
+        self.tracer = Box::new(tracer);
+        self
+    }
+
+    /// Cap the number of memory cells the program is allowed to use.
+    /// Memory grows on demand, with zeros, up to this limit:
+    /// accessing an address beyond it stops the machine with `MachineError::OutOfMemory`.
+    pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        assert!(
+            memory_limit >= self.memory_tape.len(),
+            "The memory limit cannot be smaller than the memory tape!"
+        );
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    /// Run the program to completion, feeding it `inputs`.
+    /// It returns the final memory tape and all the outputs produced along the way.
+    /// It does not pause at watchpoints.
+    pub fn execute(mut self, inputs: Vec<W>) -> Result<(Vec<W>, Vec<W>), MachineError> {
         let mut output_tape = Vec::new();
         let mut inputs = inputs.into_iter();
         loop {
-            let outcome = (&mut self).step(&mut inputs);
-            if outcome == Outcome::Halt {
-                break;
-            }
-            if let Outcome::Output(output) = outcome {
-                output_tape.push(output);
-                println!("New output: {:?}", output);
-            }
-        }
-        (self.memory_tape, output_tape)
-    }
-
-    fn step(&mut self, inputs: &mut impl Iterator<Item=i32>) -> Outcome {
-        let raw_opcode = &self.memory_tape[self.instruction_pointer];
-        let (opcode, parameter_modes) = parse_opcode(*raw_opcode);
-        println!("Current (opcode, parameter_modes): {:?}, {:?}", opcode, parameter_modes);
-        match opcode {
-            1 => {
-                let lhs = self.get_parameter(1, parameter_modes[0], false);
-                let rhs = self.get_parameter(2, parameter_modes[1], false);
-                let output_index = self.get_parameter(3, parameter_modes[2], true);
-                let output = lhs + rhs;
-                println!("Operation output value: {:?}", output);
-                self.memory_tape[output_index as usize] = output;
+            match self.run_with(&mut inputs, &mut output_tape)? {
+                Status::Halted => return Ok((self.memory_tape, output_tape)),
+                Status::Watchpoint(_) => {}
+                _ => {
+                    return Err(MachineError::InputExhausted {
+                        instruction_pointer: self.instruction_pointer,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Run the program until it halts or it needs an input that `inputs` cannot provide,
+    /// returning `Status::Halted` or `Status::NeedsInput` respectively, or until it
+    /// triggers a pausing watchpoint.
+    /// Inputs queued with `push_input` are consumed before those coming from `inputs`;
+    /// outputs are handed to `outputs` as soon as they are produced.
+    pub fn run_with(
+        &mut self,
+        inputs: &mut impl InputSource<W>,
+        outputs: &mut impl OutputSink<W>,
+    ) -> Result<Status<W>, MachineError> {
+        loop {
+            match self.step_with(inputs)? {
+                Outcome::Success => {}
+                Outcome::Output(output) => outputs.emit(output),
+                Outcome::NeedsInput => return Ok(Status::NeedsInput),
+                Outcome::Halt => return Ok(Status::Halted),
+            }
+            if let Some(hit) = self.paused_on.take() {
+                return Ok(Status::Watchpoint(hit));
+            }
+        }
+    }
+
+    /// Add a value at the back of the input queue.
+    pub fn push_input(&mut self, input: W) {
+        self.inputs.push_back(input);
+    }
+
+    /// Run the program until it produces an output, it needs an input that has not
+    /// been provided yet or it halts.
+    /// The machine keeps its state between calls: calling `run` again resumes the execution
+    /// from where it was left off.
+    /// If the program misbehaves the machine stops, leaving the instruction pointer
+    /// on the faulty instruction. It also stops after triggering a pausing watchpoint.
+    pub fn run(&mut self) -> Result<Status<W>, MachineError> {
+        loop {
+            // Checked before stepping: if the instruction that triggered the watchpoint
+            // produced an output, the output is returned first.
+            if let Some(hit) = self.paused_on.take() {
+                return Ok(Status::Watchpoint(hit));
+            }
+            match self.step_with(&mut || None)? {
+                Outcome::Success => {}
+                Outcome::Output(output) => return Ok(Status::Output(output)),
+                Outcome::NeedsInput => return Ok(Status::NeedsInput),
+                Outcome::Halt => return Ok(Status::Halted),
+            }
+        }
+    }
+
+    /// Execute the instruction under the instruction pointer.
+    /// Watchpoints never pause it: check `take_watch_hits` instead.
+    pub fn step(&mut self) -> Result<Outcome<W>, MachineError> {
+        let outcome = self.step_with(&mut || None);
+        self.paused_on = None;
+        outcome
+    }
+
+    /// Execute the instruction under the instruction pointer, drawing from `inputs`
+    /// when the input queue is empty.
+    fn step_with(&mut self, inputs: &mut dyn InputSource<W>) -> Result<Outcome<W>, MachineError> {
+        let address = self.instruction_pointer;
+        // Left over by an instruction that failed.
+        self.pending_hits.clear();
+        if let Some(log) = &mut self.undo_log {
+            log.begin(address, self.relative_base.clone(), self.memory_tape.len());
+        }
+        let Instruction {
+            opcode,
+            parameter_modes,
+        } = self.decode()?;
+        let input = if opcode == Opcode::Input {
+            match self.inputs.pop_front().or_else(|| inputs.next_input()) {
+                Some(input) => Some(input),
+                // We leave the instruction pointer untouched: the instruction
+                // will be executed again when the machine is resumed.
+                None => return Ok(Outcome::NeedsInput),
+            }
+        } else {
+            None
+        };
+        if opcode != Opcode::Halt {
+            if let Err(e) = self.check_budget() {
+                // The instruction is not executed: its input stays in the queue.
+                if let Some(input) = input {
+                    self.inputs.push_front(input);
+                }
+                return Err(e);
+            }
+        }
+        self.tracer.trace(Event::Instruction {
+            address: self.instruction_pointer,
+            opcode: opcode.code(),
+            parameter_modes,
+        });
+        let outcome = match opcode {
+            Opcode::Add => {
+                let lhs = self.get_parameter(1, parameter_modes[0], false)?;
+                let rhs = self.get_parameter(2, parameter_modes[1], false)?;
+                let output_index = self.get_parameter(3, parameter_modes[2], true)?;
+                let output = self.compute(opcode, &lhs, &rhs)?;
+                self.write(self.to_address(output_index)?, output)?;
                 self.instruction_pointer += 4;
                 Outcome::Success
             }
-            2 => {
-                let lhs = self.get_parameter(1, parameter_modes[0], false);
-                let rhs = self.get_parameter(2, parameter_modes[1], false);
-                let output_index = self.get_parameter(3, parameter_modes[2], true);
-                let output = lhs * rhs;
-                println!("Operation output value: {:?}", output);
-                self.memory_tape[output_index as usize] = output;
+            Opcode::Multiply => {
+                let lhs = self.get_parameter(1, parameter_modes[0], false)?;
+                let rhs = self.get_parameter(2, parameter_modes[1], false)?;
+                let output_index = self.get_parameter(3, parameter_modes[2], true)?;
+                let output = self.compute(opcode, &lhs, &rhs)?;
+                self.write(self.to_address(output_index)?, output)?;
                 self.instruction_pointer += 4;
                 Outcome::Success
-            },
-            3 => {
-                let output_index = self.get_parameter(1, parameter_modes[0], true);
-                let input = inputs.next().expect("Ran out of inputs!");
-                self.memory_tape[output_index as usize] = input;
+            }
+            Opcode::Input => {
+                let input = input.expect("Inputs are fetched before executing IN");
+                self.tracer.trace(Event::Input(input.clone()));
+                let written = self
+                    .get_parameter(1, parameter_modes[0], true)
+                    .and_then(|output_index| self.to_address(output_index))
+                    .and_then(|address| self.write(address, input.clone()));
+                if let Err(e) = written {
+                    // Like an instruction over budget: the input stays in the queue.
+                    self.inputs.push_front(input);
+                    return Err(e);
+                }
+                if let Some(log) = &mut self.undo_log {
+                    log.record_input(input.clone());
+                }
+                self.record(Entry::Input {
+                    step: self.steps,
+                    value: input,
+                });
                 self.instruction_pointer += 2;
-                println!("Operation output value: {:?}", input);
                 Outcome::Success
-            },
-            4 => {
-                let output_index = self.get_parameter(1, parameter_modes[0], true);
-                let output = self.memory_tape[output_index as usize];
+            }
+            Opcode::Output => {
+                let output = self.get_parameter(1, parameter_modes[0], false)?;
                 self.instruction_pointer += 2;
-                println!("Operation output value: {:?}", output);
+                self.tracer.trace(Event::Output(output.clone()));
+                self.record(Entry::Output {
+                    step: self.steps,
+                    value: output.clone(),
+                });
                 Outcome::Output(output)
-            },
-            5 => {
-                let first_parameter = self.get_parameter(1, parameter_modes[0], false);
-                let second_parameter = self.get_parameter(2, parameter_modes[1], false);
-                if first_parameter != 0 {
-                    self.instruction_pointer = second_parameter as usize;
+            }
+            Opcode::JumpIfTrue => {
+                let first_parameter = self.get_parameter(1, parameter_modes[0], false)?;
+                let second_parameter = self.get_parameter(2, parameter_modes[1], false)?;
+                if first_parameter != W::from(0) {
+                    self.instruction_pointer = self.to_address(second_parameter)?;
                 } else {
                     self.instruction_pointer += 3;
                 }
                 Outcome::Success
-            },
-            6 => {
-                let first_parameter = self.get_parameter(1, parameter_modes[0], false);
-                let second_parameter = self.get_parameter(2, parameter_modes[1], false);
-                if first_parameter == 0 {
-                    self.instruction_pointer = second_parameter as usize;
+            }
+            Opcode::JumpIfFalse => {
+                let first_parameter = self.get_parameter(1, parameter_modes[0], false)?;
+                let second_parameter = self.get_parameter(2, parameter_modes[1], false)?;
+                if first_parameter == W::from(0) {
+                    self.instruction_pointer = self.to_address(second_parameter)?;
                 } else {
                     self.instruction_pointer += 3;
                 }
                 Outcome::Success
-            },
-            7 => {
-                let first_parameter = self.get_parameter(1, parameter_modes[0], false);
-                let second_parameter = self.get_parameter(2, parameter_modes[1], false);
-                let third_parameter = self.get_parameter(3, parameter_modes[2], true);
+            }
+            Opcode::LessThan => {
+                let first_parameter = self.get_parameter(1, parameter_modes[0], false)?;
+                let second_parameter = self.get_parameter(2, parameter_modes[1], false)?;
+                let third_parameter = self.get_parameter(3, parameter_modes[2], true)?;
                 if first_parameter < second_parameter {
-                    self.memory_tape[third_parameter as usize] = 1;
+                    self.write(self.to_address(third_parameter)?, W::from(1))?;
                 } else {
-                    self.memory_tape[third_parameter as usize] = 0;
+                    self.write(self.to_address(third_parameter)?, W::from(0))?;
                 }
                 self.instruction_pointer += 4;
                 Outcome::Success
-            },
-            8 => {
-                let first_parameter = self.get_parameter(1, parameter_modes[0], false);
-                let second_parameter = self.get_parameter(2, parameter_modes[1], false);
-                let third_parameter = self.get_parameter(3, parameter_modes[2], true);
+            }
+            Opcode::Equals => {
+                let first_parameter = self.get_parameter(1, parameter_modes[0], false)?;
+                let second_parameter = self.get_parameter(2, parameter_modes[1], false)?;
+                let third_parameter = self.get_parameter(3, parameter_modes[2], true)?;
                 if first_parameter == second_parameter {
-                    self.memory_tape[third_parameter as usize] = 1;
+                    self.write(self.to_address(third_parameter)?, W::from(1))?;
                 } else {
-                    self.memory_tape[third_parameter as usize] = 0;
+                    self.write(self.to_address(third_parameter)?, W::from(0))?;
                 }
                 self.instruction_pointer += 4;
                 Outcome::Success
-            },
-            99 => Outcome::Halt,
-            _ => panic!("Unknown opcode!"),
+            }
+            Opcode::AdjustRelativeBase => {
+                let offset = self.get_parameter(1, parameter_modes[0], false)?;
+                self.relative_base = self.compute(opcode, &self.relative_base, &offset)?;
+                self.instruction_pointer += 2;
+                self.tracer.trace(Event::RelativeBase(self.relative_base.clone()));
+                Outcome::Success
+            }
+            Opcode::Halt => return Ok(Outcome::Halt),
+        };
+        self.steps += 1;
+        if let Some(profile) = &mut self.profile {
+            profile.record_instruction(address, opcode);
+        }
+        if let Some(log) = &mut self.undo_log {
+            log.commit();
         }
+        if !self.pending_hits.is_empty() {
+            self.commit_watch_hits();
+        }
+        Ok(outcome)
     }
 
-    fn get_parameter(&self, position: usize, parameter_mode: ParameterMode, is_output: bool) -> i32 {
-        match parameter_mode {
+    /// Apply the arithmetic `opcode` to its operands: ARB adds its operand to the relative base.
+    fn compute(&self, opcode: Opcode, lhs: &W, rhs: &W) -> Result<W, MachineError> {
+        let result = match (opcode, self.arithmetic) {
+            (Opcode::Add | Opcode::AdjustRelativeBase, Arithmetic::Wrapping) => {
+                Some(lhs.wrapping_add(rhs))
+            }
+            (Opcode::Add | Opcode::AdjustRelativeBase, Arithmetic::Checked) => lhs.checked_add(rhs),
+            (Opcode::Multiply, Arithmetic::Wrapping) => Some(lhs.wrapping_mul(rhs)),
+            (Opcode::Multiply, Arithmetic::Checked) => lhs.checked_mul(rhs),
+            _ => unreachable!("{:?} is not an arithmetic opcode", opcode),
+        };
+        // Only fixed-width words can overflow, and they all fit in an `i128`.
+        result.ok_or_else(|| MachineError::Overflow {
+            opcode,
+            address: self.instruction_pointer,
+            lhs: lhs.to_i128().unwrap(),
+            rhs: rhs.to_i128().unwrap(),
+        })
+    }
+
+    /// The number of instructions the machine has executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    fn check_budget(&mut self) -> Result<(), MachineError> {
+        let mut exhausted = self.step_budget.is_some_and(|budget| self.steps >= budget);
+        if let Some(time_limit) = self.time_limit {
+            let started_at = *self.started_at.get_or_insert_with(Instant::now);
+            if self.steps.is_multiple_of(TIME_CHECK_INTERVAL) && started_at.elapsed() > time_limit {
+                exhausted = true;
+            }
+        }
+        if exhausted {
+            return Err(MachineError::BudgetExhausted {
+                steps: self.steps,
+                instruction_pointer: self.instruction_pointer,
+            });
+        }
+        Ok(())
+    }
+
+    fn decode(&mut self) -> Result<Instruction, MachineError> {
+        let address = self.instruction_pointer;
+        if let Some(Some(instruction)) = self
+            .instruction_cache
+            .as_ref()
+            .and_then(|cache| cache.get(address))
+        {
+            return Ok(*instruction);
+        }
+
+        let word = self.read(address)?;
+        let raw_opcode = word.to_i64().ok_or(MachineError::UnknownOpcode {
+            opcode: saturating_to_i64(&word),
+            address,
+        })?;
+        let (code, parameter_modes) = parse_opcode(raw_opcode)
+            .map_err(|mode| MachineError::InvalidParameterMode { mode, address })?;
+        let opcode = Opcode::try_from(code)
+            .ok()
+            .filter(|&opcode| self.instruction_set.supports_opcode(opcode))
+            .ok_or(MachineError::UnknownOpcode {
+                opcode: raw_opcode,
+                address,
+            })?;
+        if let Some(mode) = (0..opcode.n_parameters())
+            .find(|&i| !self.instruction_set.supports_parameter_mode(parameter_modes[i]))
+        {
+            return Err(MachineError::InvalidParameterMode {
+                mode: raw_opcode / 10_i64.pow(mode as u32 + 2) % 10,
+                address,
+            });
+        }
+        let instruction = Instruction {
+            opcode,
+            parameter_modes,
+        };
+        // Short-lived machines, e.g. the day 7 amplifiers, never get to use the cache:
+        // only allocate it once the program has executed as many instructions as the tape
+        // has cells.
+        let warmed_up = self.steps >= self.memory_tape.len() as u64;
+        if let Some(cache) = self.instruction_cache.as_mut().filter(|_| warmed_up) {
+            if address >= cache.len() {
+                // Size the cache for the whole tape in one go rather than growing it one
+                // instruction at a time.
+                cache.resize(self.memory_tape.len().max(address + 1), None);
+            }
+            cache[address] = Some(instruction);
+        }
+        Ok(instruction)
+    }
+
+    fn get_parameter(
+        &mut self,
+        position: usize,
+        parameter_mode: ParameterMode,
+        is_output: bool,
+    ) -> Result<W, MachineError> {
+        let parameter = self.read(self.instruction_pointer + position)?;
+        let value = match parameter_mode {
             ParameterMode::Position => {
-                let index = &self.memory_tape[self.instruction_pointer + position];
-                println!("Parameter {:?}: {:?}", position, index);
                 if is_output {
-                    *index
+                    parameter
+                } else {
+                    self.read_parameter(self.to_address(parameter)?)?
+                }
+            }
+            ParameterMode::Immediate => parameter,
+            ParameterMode::Relative => {
+                let index = match self.arithmetic {
+                    Arithmetic::Wrapping => Some(self.relative_base.wrapping_add(&parameter)),
+                    Arithmetic::Checked => self.relative_base.checked_add(&parameter),
+                };
+                let index = index.ok_or_else(|| MachineError::AddressOverflow {
+                    relative_base: self.relative_base.to_i128().unwrap(),
+                    offset: parameter.to_i128().unwrap(),
+                    instruction_pointer: self.instruction_pointer,
+                })?;
+                if is_output {
+                    index
                 } else {
-                    let value = self.memory_tape[*index as usize];
-                    println!("Parameter value {:?}: {:?}", position, value);
-                    value
+                    self.read_parameter(self.to_address(index)?)?
                 }
-            },
-            ParameterMode::Immediate => {
-                let value = self.memory_tape[self.instruction_pointer + position];
-                println!("Parameter value {:?}: {:?}", position, value);
-                value
             }
+        };
+        self.tracer.trace(Event::Parameter {
+            position,
+            value: value.clone(),
+        });
+        Ok(value)
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    /// Move the instruction pointer: the next step will execute the instruction at `address`.
+    pub fn set_instruction_pointer(&mut self, address: usize) {
+        self.instruction_pointer = address;
+    }
+
+    pub fn relative_base(&self) -> W {
+        self.relative_base.clone()
+    }
+
+    /// The memory used by the program so far.
+    /// Any cell beyond its end is zero.
+    pub fn memory(&self) -> &[W] {
+        &self.memory_tape
+    }
+
+    /// Read the memory cell at `address`.
+    pub fn load(&self, address: usize) -> Result<W, MachineError> {
+        self.read(address)
+    }
+
+    /// Overwrite the memory cell at `address`, growing memory if needed.
+    /// Unlike the writes performed by the program, it is not traced.
+    pub fn store(&mut self, address: usize, value: W) -> Result<(), MachineError> {
+        self.check_memory_limit(address)?;
+        if address >= self.memory_tape.len() {
+            self.memory_tape.resize(address + 1, W::from(0));
+        }
+        self.memory_tape[address] = value;
+        // Self-modifying code: the cached instruction, if any, is stale.
+        if let Some(Some(slot)) = self
+            .instruction_cache
+            .as_mut()
+            .map(|cache| cache.get_mut(address))
+        {
+            *slot = None;
+        }
+        Ok(())
+    }
+
+    fn read(&self, address: usize) -> Result<W, MachineError> {
+        self.check_memory_limit(address)?;
+        // Cells beyond the end of the tape have never been written: they are zero.
+        Ok(self
+            .memory_tape
+            .get(address)
+            .cloned()
+            .unwrap_or_else(|| W::from(0)))
+    }
+
+    /// Read the value of a parameter in position or relative mode.
+    fn read_parameter(&mut self, address: usize) -> Result<W, MachineError> {
+        let value = self.read(address)?;
+        if let Some(profile) = &mut self.profile {
+            profile.record_read(address);
+        }
+        if !self.watchpoints.is_empty() {
+            self.check_watchpoints(address, Access::Read, &value);
+        }
+        Ok(value)
+    }
+
+    fn write(&mut self, address: usize, value: W) -> Result<(), MachineError> {
+        if self.undo_log.is_some() {
+            let previous_value = self.read(address)?;
+            if let Some(log) = &mut self.undo_log {
+                log.record_write(address, previous_value);
+            }
+        }
+        self.store(address, value.clone())?;
+        if let Some(profile) = &mut self.profile {
+            profile.record_write(address);
+        }
+        if !self.watchpoints.is_empty() {
+            self.check_watchpoints(address, Access::Write, &value);
+        }
+        self.tracer.trace(Event::Write { address, value });
+        Ok(())
+    }
+
+    fn check_memory_limit(&self, address: usize) -> Result<(), MachineError> {
+        if address >= self.memory_limit {
+            return Err(MachineError::OutOfMemory {
+                address,
+                memory_limit: self.memory_limit,
+            });
+        }
+        Ok(())
+    }
+
+    fn to_address(&self, value: W) -> Result<usize, MachineError> {
+        if value < W::from(0) {
+            return Err(MachineError::NegativeAddress {
+                address: saturating_to_i64(&value),
+                instruction_pointer: self.instruction_pointer,
+            });
+        }
+        // Too large to be an address: it is certainly beyond the memory limit.
+        let address = value
+            .to_i64()
+            .and_then(|v| usize::try_from(v).ok())
+            .unwrap_or(usize::MAX);
+        Ok(address)
+    }
+}
+
+/// Programs shared by the tests of several modules.
+#[cfg(test)]
+pub(crate) mod test_programs {
+    /// Output the running sum of the inputs, forever.
+    pub(crate) const RUNNING_SUM: [i64; 13] = [109, 20, 203, 0, 1, 20, 30, 30, 4, 30, 1105, 1, 2];
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Arithmetic, BigInt, Event, InstructionSet, MachineError, Opcode, Outcome, Status,
+        TuringMachine,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn memory_grows_past_the_end_of_the_tape() {
+        // Store 42 at address 10, read it back and output it.
+        let memory_tape = vec![1101, 40, 2, 10, 4, 10, 4, 100, 99];
+        let program = TuringMachine::new(memory_tape);
+        let (memory_tape, output_tape) = program.execute(vec![]).unwrap();
+        assert_eq!(output_tape, vec![42, 0]);
+        assert_eq!(memory_tape.len(), 11);
+    }
+
+    #[test]
+    fn memory_limit_is_enforced() {
+        let memory_tape = vec![1101, 40, 2, 1000, 99];
+        let program = TuringMachine::new(memory_tape).with_memory_limit(100);
+        let error = program.execute(vec![]).unwrap_err();
+        assert_eq!(
+            error,
+            MachineError::OutOfMemory {
+                address: 1000,
+                memory_limit: 100
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_programs_are_reported() {
+        let cases = vec![
+            (vec![1, 0, 0, 0, 42], MachineError::UnknownOpcode { opcode: 42, address: 4 }),
+            (vec![301, 0, 0, 0, 99], MachineError::InvalidParameterMode { mode: 3, address: 0 }),
+            (vec![4, -1, 99], MachineError::NegativeAddress { address: -1, instruction_pointer: 0 }),
+            (vec![3, 0, 99], MachineError::InputExhausted { instruction_pointer: 0 }),
+            // The relative base wraps around.
+            (
+                vec![109, i64::MAX, 204, 5, 99],
+                MachineError::NegativeAddress { address: i64::MIN + 4, instruction_pointer: 2 },
+            ),
+        ];
+        for (memory_tape, expected_error) in cases {
+            let program = TuringMachine::new(memory_tape);
+            assert_eq!(program.execute(vec![]).unwrap_err(), expected_error);
+        }
+
+        let cases = vec![
+            (
+                vec![109, i64::MAX, 109, 1, 99],
+                MachineError::Overflow {
+                    opcode: Opcode::AdjustRelativeBase,
+                    address: 2,
+                    lhs: i64::MAX as i128,
+                    rhs: 1,
+                },
+            ),
+            (
+                vec![109, i64::MAX, 204, 5, 99],
+                MachineError::AddressOverflow {
+                    relative_base: i64::MAX as i128,
+                    offset: 5,
+                    instruction_pointer: 2,
+                },
+            ),
+        ];
+        for (memory_tape, expected_error) in cases {
+            let program = TuringMachine::new(memory_tape).with_arithmetic(Arithmetic::Checked);
+            assert_eq!(program.execute(vec![]).unwrap_err(), expected_error);
+        }
+    }
+
+    #[test]
+    fn machine_yields_when_starved_of_inputs() {
+        // Echo every input, forever.
+        let memory_tape = vec![3, 100, 4, 100, 1105, 1, 0];
+        let mut program = TuringMachine::new(memory_tape);
+        assert_eq!(program.run().unwrap(), Status::NeedsInput);
+        program.push_input(7);
+        assert_eq!(program.run().unwrap(), Status::Output(7));
+        assert_eq!(program.run().unwrap(), Status::NeedsInput);
+        program.push_input(11);
+        assert_eq!(program.run().unwrap(), Status::Output(11));
+    }
+
+    #[test]
+    fn tracer_receives_every_event() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = {
+            let events = events.clone();
+            move |event: Event| events.lock().unwrap().push(event)
+        };
+        let memory_tape = vec![3, 5, 104, 7, 99, 0];
+        let program = TuringMachine::new(memory_tape).with_tracer(recorder);
+        program.execute(vec![3]).unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 8);
+        assert_eq!(events[1], Event::Input(3));
+        assert_eq!(events[3], Event::Write { address: 5, value: 3 });
+        assert_eq!(events[6], Event::Output(7));
+    }
+
+    #[test]
+    fn starved_instructions_are_traced_once() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = {
+            let events = events.clone();
+            move |event: Event| events.lock().unwrap().push(event)
+        };
+        let memory_tape = vec![3, 5, 99, 0, 0, 0];
+        let mut program = TuringMachine::new(memory_tape)
+            .with_tracer(recorder)
+            .with_step_budget(1);
+        assert_eq!(program.run().unwrap(), Status::NeedsInput);
+        assert_eq!(program.run().unwrap(), Status::NeedsInput);
+        program.push_input(3);
+        assert_eq!(program.run().unwrap(), Status::Halted);
+
+        let events = events.lock().unwrap();
+        let instructions: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Instruction { address, .. } => Some(*address),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(instructions, vec![0, 2]);
+    }
+
+    #[test]
+    fn single_stepping() {
+        let memory_tape = vec![1101, 2, 3, 7, 104, 5, 99, 0];
+        let mut program = TuringMachine::new(memory_tape);
+        assert_eq!(program.step().unwrap(), Outcome::Success);
+        assert_eq!(program.instruction_pointer(), 4);
+        assert_eq!(program.load(7).unwrap(), 5);
+        assert_eq!(program.step().unwrap(), Outcome::Output(5));
+        assert_eq!(program.step().unwrap(), Outcome::Halt);
+        assert_eq!(program.instruction_pointer(), 6);
+
+        program.set_instruction_pointer(4);
+        program.store(5, 8).unwrap();
+        assert_eq!(program.step().unwrap(), Outcome::Output(8));
+    }
+
+    #[test]
+    fn self_modifying_code_invalidates_the_instruction_cache() {
+        // Output 1 ten times, long enough for the first instruction to be cached,
+        // then overwrite it with 99 and jump back to it.
+        let memory_tape = vec![104, 1, 1001, 16, -1, 16, 1005, 16, 0, 1101, 99, 0, 0, 1105, 1, 0, 10];
+        let program = TuringMachine::new(memory_tape).with_step_budget(1000);
+        let (memory_tape, output_tape) = program.execute(vec![]).unwrap();
+        assert_eq!(output_tape, vec![1; 10]);
+        assert_eq!(memory_tape[0], 99);
+    }
+
+    #[test]
+    fn day2_instruction_set() {
+        let program =
+            TuringMachine::new(vec![1, 0, 0, 0, 99]).with_instruction_set(InstructionSet::Day2);
+        let (memory_tape, _) = program.execute(vec![]).unwrap();
+        assert_eq!(memory_tape, vec![2, 0, 0, 0, 99]);
+
+        let cases = vec![
+            (vec![3, 0, 99], MachineError::UnknownOpcode { opcode: 3, address: 0 }),
+            (vec![1001, 0, 1, 0, 99], MachineError::InvalidParameterMode { mode: 1, address: 0 }),
+        ];
+        for (memory_tape, expected_error) in cases {
+            let program =
+                TuringMachine::new(memory_tape).with_instruction_set(InstructionSet::Day2);
+            assert_eq!(program.execute(vec![1]).unwrap_err(), expected_error);
+        }
+    }
+
+    #[test]
+    fn step_budget_stops_infinite_loops() {
+        let memory_tape = vec![1105, 1, 0];
+        let program = TuringMachine::new(memory_tape).with_step_budget(1000);
+        assert_eq!(
+            program.execute(vec![]).unwrap_err(),
+            MachineError::BudgetExhausted {
+                steps: 1000,
+                instruction_pointer: 0
+            }
+        );
+    }
+
+    #[test]
+    fn time_limit_stops_infinite_loops() {
+        let memory_tape = vec![1105, 1, 0];
+        let program = TuringMachine::new(memory_tape).with_time_limit(Duration::from_millis(10));
+        match program.execute(vec![]).unwrap_err() {
+            MachineError::BudgetExhausted { steps, .. } => assert!(steps > 0),
+            e => panic!("Unexpected error: {}", e),
         }
     }
+
+    #[test]
+    fn overflow() {
+        // Output the product of the two inputs.
+        let product = vec![3, 11, 3, 12, 2, 11, 12, 11, 4, 11, 99, 0, 0];
+        let inputs = vec![i64::MAX, 2];
+
+        let program = TuringMachine::new(product.clone());
+        assert_eq!(program.execute(inputs.clone()).unwrap().1, vec![-2]);
+
+        let program = TuringMachine::new(product.clone()).with_arithmetic(Arithmetic::Checked);
+        let error = program.execute(inputs).unwrap_err();
+        assert_eq!(
+            error,
+            MachineError::Overflow {
+                opcode: Opcode::Multiply,
+                address: 4,
+                lhs: i64::MAX as i128,
+                rhs: 2
+            }
+        );
+        assert_eq!(error.to_string(), "MUL 9223372036854775807, 2 overflowed at address 4");
+
+        let program = TuringMachine::new(product).with_arithmetic(Arithmetic::Checked);
+        assert_eq!(program.execute(vec![1 << 31, 1 << 31]).unwrap().1, vec![1 << 62]);
+    }
+
+    #[test]
+    fn word_types() {
+        // Output the square of the input.
+        let square: Vec<i64> = vec![3, 9, 2, 9, 9, 9, 4, 9, 99, 0];
+
+        let program = TuringMachine::<i32>::from_words(square.iter().map(|&w| w as i32).collect());
+        assert_eq!(program.execute(vec![-7]).unwrap().1, vec![49]);
+
+        let program = TuringMachine::<i128>::from_words(square.iter().map(|&w| w.into()).collect());
+        assert_eq!(program.execute(vec![1 << 40]).unwrap().1, vec![1 << 80]);
+
+        // Too large to be decoded.
+        let program = TuringMachine::<i128>::from_words(vec![i128::MAX]);
+        assert_eq!(
+            program.execute(vec![]).unwrap_err(),
+            MachineError::UnknownOpcode { opcode: i64::MAX, address: 0 }
+        );
+
+        let program = TuringMachine::from_words(square.into_iter().map(BigInt::from).collect());
+        let input = BigInt::from(1_u128 << 100);
+        let expected = "1606938044258990275541962092341162602522202993782792835301376";
+        let (_, output_tape) = program.execute(vec![input]).unwrap();
+        assert_eq!(output_tape, vec![expected.parse::<BigInt>().unwrap()]);
+    }
 }
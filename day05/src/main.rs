@@ -1,12 +1,12 @@
 use std::str::FromStr;
 use day05::TuringMachine;
 
-fn read_input(path: &str) -> Vec<i32> {
+fn read_input(path: &str) -> Vec<i64> {
     let input = std::fs::read_to_string(path).expect("Failed to read input");
     let instructions = input
         .trim()
         .split(",")
-        .map(|s| i32::from_str(&s).expect("Failed to parse instruction"))
+        .map(|s| i64::from_str(s).expect("Failed to parse instruction"))
         .collect();
     instructions
 }
@@ -17,11 +17,11 @@ fn main() {
 
     // First part
     let program = TuringMachine::new(memory_tape.clone());
-    let (_, output_tape) = program.execute(vec![1]);
+    let (_, output_tape) = program.execute(vec![1]).expect("The program failed");
     println!("Output tape: {:?}", output_tape);
 
     // Second part
     let program = TuringMachine::new(memory_tape.clone());
-    let (_, output_tape) = program.execute(vec![5]);
+    let (_, output_tape) = program.execute(vec![5]).expect("The program failed");
     println!("Output tape: {:?}", output_tape);
 }
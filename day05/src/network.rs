@@ -0,0 +1,239 @@
+//! Wire several `TuringMachine`s together: the outputs of a machine become the inputs
+//! of the machines it is connected to.
+//!
+//! Machines are run one at a time, in the order they were added, until none of them
+//! can make progress: every machine has either halted or is waiting for an input
+//! that nobody is going to send.
+use crate::{MachineError, Status, TuringMachine};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Identifies a machine within a `Network`.
+pub type NodeId = usize;
+
+/// A machine failed while the network was running.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NetworkError {
+    pub node: NodeId,
+    pub error: MachineError,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Machine {} failed: {}", self.node, self.error)
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// Why `Network::run` stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Quiescence {
+    /// Every machine has halted.
+    Halted,
+    /// The listed machines are waiting for inputs, all the others have halted.
+    Waiting(Vec<NodeId>),
+}
+
+struct Node {
+    machine: TuringMachine,
+    inputs: VecDeque<i64>,
+    targets: Vec<NodeId>,
+    /// `Some` for sink nodes: everything they output, across runs.
+    collected: Option<Vec<i64>>,
+    status: Option<Status>,
+}
+
+impl Node {
+    fn is_runnable(&self) -> bool {
+        match self.status {
+            None => true,
+            Some(Status::Halted) => false,
+            Some(Status::Watchpoint(_)) => true,
+            Some(_) => !self.inputs.is_empty(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Network {
+    nodes: Vec<Node>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_machine(&mut self, machine: TuringMachine) -> NodeId {
+        self.nodes.push(Node {
+            machine,
+            inputs: VecDeque::new(),
+            targets: vec![],
+            collected: None,
+            status: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Send every output of `from` to `to`.
+    /// A machine can be connected to several machines (each of them gets a copy of its
+    /// outputs) and several machines can be connected to the same one (their outputs
+    /// are interleaved in the order they are produced). Cycles are allowed.
+    pub fn connect(&mut self, from: NodeId, to: NodeId) -> &mut Self {
+        assert!(to < self.nodes.len(), "There is no machine with id {}", to);
+        self.nodes[from].targets.push(to);
+        self
+    }
+
+    /// Queue an input for `node`, to be consumed before anything sent by other machines
+    /// after this call.
+    pub fn seed(&mut self, node: NodeId, input: i64) -> &mut Self {
+        self.nodes[node].inputs.push_back(input);
+        self
+    }
+
+    /// Keep track of the outputs of `node`, to be retrieved with `outputs`.
+    /// They are still forwarded to the machines `node` is connected to.
+    pub fn collect(&mut self, node: NodeId) -> &mut Self {
+        self.nodes[node].collected.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// The outputs of a sink node so far.
+    pub fn outputs(&self, node: NodeId) -> &[i64] {
+        self.nodes[node]
+            .collected
+            .as_deref()
+            .expect("Outputs are only collected for sink nodes")
+    }
+
+    pub fn machine(&mut self, node: NodeId) -> &mut TuringMachine {
+        &mut self.nodes[node].machine
+    }
+
+    /// Run the network until no machine can make progress.
+    /// It can be called again after seeding more inputs.
+    pub fn run(&mut self) -> Result<Quiescence, NetworkError> {
+        let mut outputs = Vec::new();
+        loop {
+            let mut progress = false;
+            for id in 0..self.nodes.len() {
+                let node = &mut self.nodes[id];
+                if !node.is_runnable() {
+                    continue;
+                }
+                progress = true;
+                let result = node.machine.run_with(&mut node.inputs, &mut outputs);
+                // Outputs produced before a failure are delivered all the same.
+                if let Some(collected) = &mut node.collected {
+                    collected.extend_from_slice(&outputs);
+                }
+                for target in node.targets.clone() {
+                    self.nodes[target].inputs.extend(outputs.iter().copied());
+                }
+                outputs.clear();
+                let status = result.map_err(|error| NetworkError { node: id, error })?;
+                self.nodes[id].status = Some(status);
+            }
+            if !progress {
+                break;
+            }
+        }
+
+        let waiting: Vec<NodeId> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.status != Some(Status::Halted))
+            .map(|(id, _)| id)
+            .collect();
+        if waiting.is_empty() {
+            Ok(Quiescence::Halted)
+        } else {
+            Ok(Quiescence::Waiting(waiting))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+    use crate::network::{Network, NetworkError, Quiescence};
+    use crate::test_programs::RUNNING_SUM;
+    use crate::{MachineError, TuringMachine};
+
+    #[test]
+    fn fan_out_and_fan_in() {
+        // Double the input, then halt.
+        let double = assemble("IN x\nMUL x, #2, x\nOUT x\nHLT\nx: data 0").unwrap();
+
+        let mut network = Network::new();
+        let source = network.add_machine(TuringMachine::new(double.clone()));
+        let left = network.add_machine(TuringMachine::new(double.clone()));
+        let right = network.add_machine(TuringMachine::new(double));
+        let sum = network.add_machine(TuringMachine::new(RUNNING_SUM.to_vec()));
+        network
+            .connect(source, left)
+            .connect(source, right)
+            .connect(left, sum)
+            .connect(right, sum)
+            .collect(sum)
+            .seed(source, 5);
+
+        assert_eq!(network.run().unwrap(), Quiescence::Waiting(vec![sum]));
+        assert_eq!(network.outputs(sum), &[20, 40]);
+
+        network.seed(sum, 1);
+        network.run().unwrap();
+        assert_eq!(network.outputs(sum), &[20, 40, 41]);
+    }
+
+    #[test]
+    fn cycles() {
+        // Two running sums feeding each other: every other Fibonacci number.
+        let mut network = Network::new();
+        let a = network.add_machine(TuringMachine::new(RUNNING_SUM.to_vec()));
+        let b = network.add_machine(TuringMachine::new(RUNNING_SUM.to_vec()).with_step_budget(100));
+        network.connect(a, b).connect(b, a).collect(b).seed(a, 1);
+
+        let error = network.run().unwrap_err();
+        assert_eq!(error.node, b);
+        assert!(matches!(
+            error,
+            NetworkError {
+                error: MachineError::BudgetExhausted { .. },
+                ..
+            }
+        ));
+        let expected = [
+            1, 3, 8, 21, 55, 144, 377, 987, 2584, 6765, 17711, 46368, 121393, 317811, 832040,
+            2178309, 5702887, 14930352, 39088169, 102334155, 267914296, 701408733, 1836311903,
+            4807526976, 12586269025,
+        ];
+        assert_eq!(network.outputs(b), &expected[..]);
+    }
+
+    #[test]
+    fn outputs_of_failed_machines_are_kept() {
+        let mut network = Network::new();
+        let b = network.add_machine(TuringMachine::new(RUNNING_SUM.to_vec()));
+        let a = network.add_machine(TuringMachine::new(vec![104, 1, 104, 2, 77]));
+        network.connect(a, b).collect(a).collect(b);
+
+        let error = network.run().unwrap_err();
+        assert_eq!(error.node, a);
+        assert!(matches!(
+            error,
+            NetworkError {
+                error: MachineError::UnknownOpcode { .. },
+                ..
+            }
+        ));
+        assert_eq!(network.outputs(a), &[1, 2]);
+
+        // They were sent to `b` too.
+        network.run().unwrap_err();
+        assert_eq!(network.outputs(b), &[1, 3]);
+    }
+}
@@ -0,0 +1,358 @@
+//! A network of Intcode computers exchanging packets through their network interface
+//! controllers (NICs).
+//!
+//! Every computer is booted with its network address as first input. It then sends
+//! packets as triples of outputs, `destination, x, y`, and receives the `x, y` pairs
+//! of the packets sent to it. Reading from an empty queue does not block: the program
+//! gets `-1` instead.
+//!
+//! Packets sent to `NAT_ADDRESS` are stored by the NAT, which only keeps the last one.
+//! When the network is idle - all queues are empty and every computer is polling for
+//! packets without sending any - the NAT wakes it up by sending its packet to address 0.
+//!
+//! Computers are scheduled in rounds: in each round every computer, in address order,
+//! runs until it polls an empty queue a second time. Packets are delivered as soon as
+//! they are sent, hence runs are fully reproducible.
+//! A computer that executes more than its turn budget without polling is reported as
+//! hung, instead of blocking the whole network.
+use crate::{MachineError, Status, TuringMachine};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+
+pub const NAT_ADDRESS: i64 = 255;
+
+/// How many instructions a computer can execute in a turn, unless configured otherwise
+/// with `Router::with_turn_budget`.
+pub const DEFAULT_TURN_BUDGET: u64 = 1_000_000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Packet {
+    pub destination: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+/// What moved over the network during a round.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Traffic {
+    /// A packet from `source` to another computer.
+    Sent { source: usize, packet: Packet },
+    /// A packet from `source` to the NAT.
+    ToNat { source: usize, packet: Packet },
+    /// The network was idle: the NAT sent its last packet to address 0.
+    WakeUp(Packet),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RouterError {
+    /// The computer at `address` failed.
+    Machine { address: usize, error: MachineError },
+    /// The computer at `address` used up its turn budget without polling for packets.
+    Hung { address: usize },
+    /// The computer at `source` sent a packet to an address that does not exist.
+    UnknownDestination { source: usize, packet: Packet },
+    /// The network is idle and the NAT has no packet to wake it up with.
+    Idle,
+    /// The network is idle and the NAT cannot wake it up: computer 0 has halted.
+    Halted,
+}
+
+impl fmt::Display for RouterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouterError::Machine { address, error } => {
+                write!(f, "Computer {} failed: {}", address, error)
+            }
+            RouterError::Hung { address } => write!(
+                f,
+                "Computer {} used up its turn budget without polling for packets",
+                address
+            ),
+            RouterError::UnknownDestination { source, packet } => write!(
+                f,
+                "Computer {} sent a packet to the unknown address {}",
+                source, packet.destination
+            ),
+            RouterError::Idle => write!(f, "The network is idle and the NAT has nothing to send"),
+            RouterError::Halted => write!(
+                f,
+                "The network is idle and computer 0, which the NAT wakes it up through, has halted"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RouterError {}
+
+struct Nic {
+    machine: TuringMachine,
+    queue: VecDeque<i64>,
+    /// Outputs that do not make up a full packet yet.
+    partial: Vec<i64>,
+    halted: bool,
+}
+
+pub struct Router {
+    nics: Vec<Nic>,
+    nat: Option<Packet>,
+    turn_budget: u64,
+}
+
+impl Router {
+    /// Boot `n_computers` computers running `program`, with addresses `0..n_computers`.
+    pub fn new(program: &[i64], n_computers: usize) -> Self {
+        assert!(
+            (n_computers as i64) <= NAT_ADDRESS,
+            "The NAT address must not be used by a computer"
+        );
+        let nics = (0..n_computers)
+            .map(|address| Nic {
+                machine: TuringMachine::new(program.to_vec()),
+                queue: vec![address as i64].into_iter().collect(),
+                partial: vec![],
+                halted: false,
+            })
+            .collect();
+        Self {
+            nics,
+            nat: None,
+            turn_budget: DEFAULT_TURN_BUDGET,
+        }
+    }
+
+    /// Report a computer as hung once it executes `turn_budget` instructions in a turn.
+    pub fn with_turn_budget(mut self, turn_budget: u64) -> Self {
+        self.turn_budget = turn_budget;
+        self
+    }
+
+    /// The last packet received by the NAT, if any.
+    pub fn nat_packet(&self) -> Option<Packet> {
+        self.nat
+    }
+
+    /// Give every computer a turn, then wake up the network if it is idle.
+    pub fn round(&mut self) -> Result<Vec<Traffic>, RouterError> {
+        let mut traffic = vec![];
+        let mut idle = true;
+        let mut outputs = vec![];
+        let turn_budget = self.turn_budget;
+        for address in 0..self.nics.len() {
+            let nic = &mut self.nics[address];
+            if nic.halted {
+                continue;
+            }
+            nic.machine.step_budget = Some(nic.machine.steps() + turn_budget);
+            let queue = &mut nic.queue;
+            let mut received = false;
+            let mut polled = false;
+            let mut input = || match queue.pop_front() {
+                Some(value) => {
+                    received = true;
+                    Some(value)
+                }
+                None if !polled => {
+                    polled = true;
+                    Some(-1)
+                }
+                None => None,
+            };
+            let status = nic
+                .machine
+                .run_with(&mut input, &mut outputs)
+                .map_err(|error| match error {
+                    MachineError::BudgetExhausted { .. } => RouterError::Hung { address },
+                    error => RouterError::Machine { address, error },
+                })?;
+            nic.halted = status == Status::Halted;
+            if received || !outputs.is_empty() {
+                idle = false;
+            }
+
+            nic.partial.append(&mut outputs);
+            let partial = std::mem::take(&mut nic.partial);
+            let mut packets = partial.chunks_exact(3);
+            for chunk in &mut packets {
+                let packet = Packet {
+                    destination: chunk[0],
+                    x: chunk[1],
+                    y: chunk[2],
+                };
+                traffic.push(self.route(address, packet)?);
+            }
+            self.nics[address].partial = packets.remainder().to_vec();
+        }
+
+        if idle && self.nics.iter().all(|nic| nic.halted || nic.queue.is_empty()) {
+            let packet = self.nat.ok_or(RouterError::Idle)?;
+            if self.nics[0].halted {
+                return Err(RouterError::Halted);
+            }
+            self.nics[0].queue.extend(&[packet.x, packet.y]);
+            traffic.push(Traffic::WakeUp(packet));
+        }
+        Ok(traffic)
+    }
+
+    /// Run rounds until `monitor` returns something for one of the packets on the network.
+    pub fn run_until<T>(
+        &mut self,
+        mut monitor: impl FnMut(&Traffic) -> Option<T>,
+    ) -> Result<T, RouterError> {
+        loop {
+            for traffic in self.round()? {
+                if let Some(result) = monitor(&traffic) {
+                    return Ok(result);
+                }
+            }
+        }
+    }
+
+    fn route(&mut self, source: usize, packet: Packet) -> Result<Traffic, RouterError> {
+        if packet.destination == NAT_ADDRESS {
+            self.nat = Some(packet);
+            return Ok(Traffic::ToNat { source, packet });
+        }
+        let nic = usize::try_from(packet.destination)
+            .ok()
+            .and_then(|destination| self.nics.get_mut(destination))
+            .ok_or(RouterError::UnknownDestination { source, packet })?;
+        nic.queue.extend(&[packet.x, packet.y]);
+        Ok(Traffic::Sent { source, packet })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+    use crate::nic::{Packet, Router, RouterError, Traffic, NAT_ADDRESS};
+    use crate::MachineError;
+
+    const N_COMPUTERS: i64 = 50;
+
+    // Computer 0 sends `0, 0` to computer 1. Every computer forwards the packets it
+    // receives to the next one, incrementing `y`: the last one sends them to the NAT.
+    fn relay() -> Vec<i64> {
+        let source = format!(
+            "
+                    IN address
+                    JNZ address, #receive
+                    OUT #1
+                    OUT #0
+                    OUT #0
+            receive: IN x
+                    EQ x, #-1, tmp
+                    JNZ tmp, #receive
+                    IN y
+                    ADD address, #1, next
+                    EQ next, #{}, tmp
+                    JZ tmp, #send
+                    ADD #{}, #0, next
+            send:   OUT next
+                    OUT x
+                    ADD y, #1, y
+                    OUT y
+                    JZ #0, #receive
+            address: data 0
+            x:      data 0
+            y:      data 0
+            next:   data 0
+            tmp:    data 0
+            ",
+            N_COMPUTERS, NAT_ADDRESS
+        );
+        assemble(&source).unwrap()
+    }
+
+    #[test]
+    fn packets_reach_the_nat() {
+        let mut router = Router::new(&relay(), N_COMPUTERS as usize);
+        let packet = router
+            .run_until(|traffic| match traffic {
+                Traffic::ToNat { source, packet } => Some((*source, *packet)),
+                _ => None,
+            })
+            .unwrap();
+        let expected = Packet {
+            destination: NAT_ADDRESS,
+            x: 0,
+            y: N_COMPUTERS - 1,
+        };
+        assert_eq!(packet, (N_COMPUTERS as usize - 1, expected));
+        assert_eq!(router.nat_packet(), Some(expected));
+    }
+
+    #[test]
+    fn the_nat_wakes_up_idle_networks() {
+        let mut router = Router::new(&relay(), N_COMPUTERS as usize);
+        let mut wake_ups = vec![];
+        let last = router
+            .run_until(|traffic| match traffic {
+                Traffic::WakeUp(packet) => {
+                    wake_ups.push(packet.y);
+                    if wake_ups.len() == 3 {
+                        Some(packet.y)
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(last, 3 * N_COMPUTERS - 1);
+        assert_eq!(
+            wake_ups,
+            vec![N_COMPUTERS - 1, 2 * N_COMPUTERS - 1, 3 * N_COMPUTERS - 1]
+        );
+    }
+
+    #[test]
+    fn the_nat_cannot_wake_up_a_halted_computer() {
+        // Computer 0 sends a packet to the NAT and halts, the others poll forever.
+        let source = "
+                    IN address
+                    JNZ address, #poll
+                    OUT #255
+                    OUT #1
+                    OUT #2
+                    HLT
+            poll:   IN x
+                    JZ #0, #poll
+            address: data 0
+            x:      data 0
+        ";
+        let mut router = Router::new(&assemble(source).unwrap(), 3);
+        let error = router.run_until(|_| None::<()>).unwrap_err();
+        assert_eq!(error, RouterError::Halted);
+        assert_eq!(router.nat_packet().map(|packet| packet.y), Some(2));
+    }
+
+    #[test]
+    fn hung_computers_are_reported() {
+        // Computer 1 loops forever without polling for packets.
+        let source = "
+                    IN address
+                    JNZ address, #loop
+            poll:   IN x
+                    JZ #0, #poll
+            loop:   JZ #0, #loop
+            address: data 0
+            x:      data 0
+        ";
+        let mut router = Router::new(&assemble(source).unwrap(), 2).with_turn_budget(1000);
+        let error = router.round().unwrap_err();
+        assert_eq!(error, RouterError::Hung { address: 1 });
+
+        // Other failures are reported as they are.
+        let mut router = Router::new(&[3, 100, 77], 1);
+        let error = router.round().unwrap_err();
+        assert!(matches!(
+            error,
+            RouterError::Machine {
+                address: 0,
+                error: MachineError::UnknownOpcode { .. }
+            }
+        ));
+    }
+}
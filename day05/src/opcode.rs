@@ -0,0 +1,137 @@
+use crate::ParameterMode;
+use std::convert::TryFrom;
+
+/// The instructions understood by a `TuringMachine`, ordered by numeric code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Halt,
+}
+
+impl Opcode {
+    pub const ALL: [Opcode; 10] = [
+        Opcode::Add,
+        Opcode::Multiply,
+        Opcode::Input,
+        Opcode::Output,
+        Opcode::JumpIfTrue,
+        Opcode::JumpIfFalse,
+        Opcode::LessThan,
+        Opcode::Equals,
+        Opcode::AdjustRelativeBase,
+        Opcode::Halt,
+    ];
+
+    /// The numeric code of the instruction, i.e. the last two digits of its first word.
+    pub fn code(self) -> u32 {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Multiply => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::Equals => 8,
+            Opcode::AdjustRelativeBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "ADD",
+            Opcode::Multiply => "MUL",
+            Opcode::Input => "IN",
+            Opcode::Output => "OUT",
+            Opcode::JumpIfTrue => "JNZ",
+            Opcode::JumpIfFalse => "JZ",
+            Opcode::LessThan => "LT",
+            Opcode::Equals => "EQ",
+            Opcode::AdjustRelativeBase => "ARB",
+            Opcode::Halt => "HLT",
+        }
+    }
+
+    /// Look up an opcode by mnemonic, ignoring case.
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        Opcode::ALL
+            .iter()
+            .copied()
+            .find(|opcode| opcode.mnemonic().eq_ignore_ascii_case(mnemonic))
+    }
+
+    pub fn n_parameters(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustRelativeBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// The position (1-based) of the parameter the instruction writes to, if any.
+    pub fn output_parameter(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => Some(3),
+            Opcode::Input => Some(1),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<u32> for Opcode {
+    /// The unknown opcode.
+    type Error = u32;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        match code {
+            1 => Ok(Opcode::Add),
+            2 => Ok(Opcode::Multiply),
+            3 => Ok(Opcode::Input),
+            4 => Ok(Opcode::Output),
+            5 => Ok(Opcode::JumpIfTrue),
+            6 => Ok(Opcode::JumpIfFalse),
+            7 => Ok(Opcode::LessThan),
+            8 => Ok(Opcode::Equals),
+            9 => Ok(Opcode::AdjustRelativeBase),
+            99 => Ok(Opcode::Halt),
+            _ => Err(code),
+        }
+    }
+}
+
+/// The instructions a `TuringMachine` accepts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InstructionSet {
+    /// Every opcode and parameter mode.
+    Full,
+    /// The subset from day 2: ADD, MUL and HLT, with parameters in position mode.
+    Day2,
+}
+
+impl InstructionSet {
+    pub fn supports_opcode(self, opcode: Opcode) -> bool {
+        match self {
+            InstructionSet::Full => true,
+            InstructionSet::Day2 => {
+                matches!(opcode, Opcode::Add | Opcode::Multiply | Opcode::Halt)
+            }
+        }
+    }
+
+    pub fn supports_parameter_mode(self, parameter_mode: ParameterMode) -> bool {
+        match self {
+            InstructionSet::Full => true,
+            InstructionSet::Day2 => parameter_mode == ParameterMode::Position,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Find out where an Intcode program spends its time.
+use crate::disassembler::decode;
+use crate::{saturating_to_i64, Opcode, TuringMachine, Word};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Execution counts collected by a `TuringMachine` built `with_profiling`.
+///
+/// Only executed instructions are counted, hence a `HLT` never is.
+/// Reads are the memory accesses of parameters in position or relative mode: fetching
+/// instructions and their immediate parameters does not count.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    executions: HashMap<usize, u64>,
+    opcodes: HashMap<Opcode, u64>,
+    reads: HashMap<usize, u64>,
+    writes: HashMap<usize, u64>,
+}
+
+impl Profile {
+    pub(crate) fn record_instruction(&mut self, address: usize, opcode: Opcode) {
+        *self.executions.entry(address).or_default() += 1;
+        *self.opcodes.entry(opcode).or_default() += 1;
+    }
+
+    pub(crate) fn record_read(&mut self, address: usize) {
+        *self.reads.entry(address).or_default() += 1;
+    }
+
+    pub(crate) fn record_write(&mut self, address: usize) {
+        *self.writes.entry(address).or_default() += 1;
+    }
+
+    /// The total number of instructions executed.
+    pub fn instructions(&self) -> u64 {
+        self.executions.values().sum()
+    }
+
+    /// How many times the instruction at `address` has been executed.
+    pub fn executions(&self, address: usize) -> u64 {
+        self.executions.get(&address).copied().unwrap_or(0)
+    }
+
+    /// How many instructions with `opcode` have been executed.
+    pub fn opcode_executions(&self, opcode: Opcode) -> u64 {
+        self.opcodes.get(&opcode).copied().unwrap_or(0)
+    }
+
+    pub fn reads(&self, address: usize) -> u64 {
+        self.reads.get(&address).copied().unwrap_or(0)
+    }
+
+    pub fn writes(&self, address: usize) -> u64 {
+        self.writes.get(&address).copied().unwrap_or(0)
+    }
+
+    /// Executed addresses with their execution count, most executed first.
+    pub fn hot_spots(&self) -> Vec<(usize, u64)> {
+        sorted_by_count(self.executions.iter().map(|(&a, &c)| (a, c)))
+    }
+
+    /// A human-readable summary: the `n` most executed instructions, disassembled from
+    /// `memory`, the number of executions per opcode and the `n` most accessed memory cells.
+    /// Operands that do not fit in an `i64` are shown clamped to `i64::MIN` or `i64::MAX`.
+    pub fn report<W: Word>(&self, memory: &[W], n: usize) -> String {
+        let memory: Vec<i64> = memory.iter().map(saturating_to_i64).collect();
+        let total = self.instructions();
+        let percentage = |count: u64| 100. * count as f64 / total.max(1) as f64;
+        let mut report = String::new();
+        writeln!(report, "{} instructions executed", total).unwrap();
+
+        writeln!(report, "\nHot spots:").unwrap();
+        for (address, count) in self.hot_spots().into_iter().take(n) {
+            writeln!(
+                report,
+                "{:>12} {:>6.2}% {:>5}: {}",
+                count,
+                percentage(count),
+                address,
+                decode(&memory, address)
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "\nOpcodes:").unwrap();
+        for (opcode, count) in sorted_by_count(self.opcodes.iter().map(|(&o, &c)| (o, c))) {
+            writeln!(
+                report,
+                "{:>12} {:>6.2}% {}",
+                count,
+                percentage(count),
+                opcode.mnemonic()
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "\nMemory cells:").unwrap();
+        let mut accesses: HashMap<usize, u64> = self.reads.clone();
+        for (&address, &count) in &self.writes {
+            *accesses.entry(address).or_default() += count;
+        }
+        for (address, _) in sorted_by_count(accesses).into_iter().take(n) {
+            writeln!(
+                report,
+                "{:>5}: {} reads, {} writes",
+                address,
+                self.reads(address),
+                self.writes(address)
+            )
+            .unwrap();
+        }
+        report
+    }
+}
+
+/// Most frequent first, ties broken by key.
+fn sorted_by_count<K: Ord>(counts: impl IntoIterator<Item = (K, u64)>) -> Vec<(K, u64)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    counts
+}
+
+impl<W: Word> TuringMachine<W> {
+    /// Count instructions and memory accesses while the program runs.
+    /// Retrieve the counts with `profile`.
+    pub fn with_profiling(mut self) -> Self {
+        self.profile = Some(Profile::default());
+        self
+    }
+
+    /// `None` if profiling is disabled.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+    use crate::{Opcode, TuringMachine};
+
+    #[test]
+    fn hot_loop() {
+        let source = "
+                    IN counter
+            loop:   ADD counter, #-1, counter
+                    JNZ counter, #loop
+                    OUT counter
+                    HLT
+            counter: data 0
+        ";
+        let mut program = TuringMachine::new(assemble(source).unwrap()).with_profiling();
+        program.push_input(10);
+        program.run().unwrap();
+        program.run().unwrap();
+
+        let profile = program.profile().unwrap();
+        assert_eq!(profile.instructions(), 22);
+        assert_eq!(profile.instructions(), program.steps());
+        assert_eq!(profile.hot_spots(), vec![(2, 10), (6, 10), (0, 1), (9, 1)]);
+        assert_eq!(profile.opcode_executions(Opcode::Add), 10);
+        assert_eq!(profile.opcode_executions(Opcode::Halt), 0);
+        assert_eq!(profile.reads(12), 21);
+        assert_eq!(profile.writes(12), 11);
+
+        let report = profile.report(program.memory(), 2);
+        let expected = "\
+22 instructions executed
+
+Hot spots:
+          10  45.45%     2: ADD 12, #-1, 12
+          10  45.45%     6: JNZ 12, #2
+
+Opcodes:
+          10  45.45% ADD
+          10  45.45% JNZ
+           1   4.55% IN
+           1   4.55% OUT
+
+Memory cells:
+   12: 21 reads, 11 writes
+";
+        assert_eq!(report, expected);
+
+        let memory_tape = assemble(source).unwrap().into_iter().map(i128::from).collect();
+        let mut program = TuringMachine::<i128>::from_words(memory_tape).with_profiling();
+        program.push_input(10);
+        program.run().unwrap();
+        program.run().unwrap();
+        assert_eq!(program.profile().unwrap().report(program.memory(), 2), expected);
+    }
+}
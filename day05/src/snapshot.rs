@@ -0,0 +1,274 @@
+//! Save and restore the full state of a `TuringMachine`.
+//!
+//! Snapshots are stored as plain text, one `key value` pair per line:
+//!
+//! ```text
+//! intcode-snapshot 2
+//! instruction_pointer 2
+//! relative_base 0
+//! memory_limit 16777216
+//! instruction_set full
+//! arithmetic wrapping
+//! steps 5
+//! step_budget none
+//! time_limit_ms 60000
+//! elapsed_ms 12
+//! inputs 5,7
+//! memory 3,9,4,9,99,0,0,0,0,3
+//! ```
+use crate::format::{self, Format, ParseError};
+use crate::{Arithmetic, InstructionSet, TuringMachine, Word};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+const FORMAT: Format = Format {
+    name: "snapshot",
+    header: "intcode-snapshot",
+    version: 2,
+};
+
+/// Everything that is needed to resume a `TuringMachine` where it was left off.
+/// Outputs are handed to the caller as soon as they are produced, hence the only
+/// pending I/O is the queue of inputs the program has not consumed yet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot<W = i64> {
+    pub memory: Vec<W>,
+    pub instruction_pointer: usize,
+    pub relative_base: W,
+    pub memory_limit: usize,
+    pub instruction_set: InstructionSet,
+    pub arithmetic: Arithmetic,
+    /// The number of instructions executed so far.
+    pub steps: u64,
+    pub step_budget: Option<u64>,
+    pub time_limit: Option<Duration>,
+    /// The time elapsed since the machine executed its first instruction, if it has a
+    /// time limit: a restored machine only gets what is left of it.
+    pub elapsed: Duration,
+    pub pending_inputs: Vec<W>,
+}
+
+impl<W: Word> Snapshot<W> {
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        format::save(self, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        format::load(path)
+    }
+}
+
+fn join<W: fmt::Display>(values: &[W]) -> String {
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    values.join(",")
+}
+
+fn optional<T: fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "none".to_string(), |v| v.to_string())
+}
+
+impl<W: fmt::Display> fmt::Display for Snapshot<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let instruction_set = match self.instruction_set {
+            InstructionSet::Full => "full",
+            InstructionSet::Day2 => "day2",
+        };
+        let arithmetic = match self.arithmetic {
+            Arithmetic::Wrapping => "wrapping",
+            Arithmetic::Checked => "checked",
+        };
+        FORMAT.write_header(f)?;
+        writeln!(f, "instruction_pointer {}", self.instruction_pointer)?;
+        writeln!(f, "relative_base {}", self.relative_base)?;
+        writeln!(f, "memory_limit {}", self.memory_limit)?;
+        writeln!(f, "instruction_set {}", instruction_set)?;
+        writeln!(f, "arithmetic {}", arithmetic)?;
+        writeln!(f, "steps {}", self.steps)?;
+        writeln!(f, "step_budget {}", optional(self.step_budget))?;
+        writeln!(f, "time_limit_ms {}", optional(self.time_limit.map(|t| t.as_millis())))?;
+        writeln!(f, "elapsed_ms {}", self.elapsed.as_millis())?;
+        writeln!(f, "inputs {}", join(&self.pending_inputs))?;
+        writeln!(f, "memory {}", join(&self.memory))
+    }
+}
+
+impl<W: Word> FromStr for Snapshot<W> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = FORMAT.check_header(s)?;
+        let mut fields = HashMap::new();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let mut parts = line.splitn(2, ' ');
+            let key = parts.next().unwrap();
+            let value = parts.next().unwrap_or("").trim();
+            fields.insert(key, value);
+        }
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .copied()
+                .ok_or_else(|| FORMAT.error(format!("missing `{}`", key)))
+        };
+        fn invalid(key: &str, value: &str) -> ParseError {
+            FORMAT.error(format!("invalid `{}`: {}", key, value))
+        }
+        fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, ParseError> {
+            T::from_str(value).map_err(|_| invalid(key, value))
+        }
+        let parse_optional = |key: &str| -> Result<Option<u64>, ParseError> {
+            match field(key)? {
+                "none" => Ok(None),
+                value => parse(key, value).map(Some),
+            }
+        };
+        let parse_list = |key: &str| -> Result<Vec<W>, ParseError> {
+            let value = field(key)?;
+            if value.is_empty() {
+                return Ok(vec![]);
+            }
+            value.split(',').map(|v| parse(key, v)).collect()
+        };
+
+        let memory = parse_list("memory")?;
+        if memory.is_empty() {
+            return Err(FORMAT.error("the memory cannot be empty"));
+        }
+        let memory_limit = parse("memory_limit", field("memory_limit")?)?;
+        if memory.len() > memory_limit {
+            return Err(FORMAT.error("the memory exceeds the memory limit"));
+        }
+        let instruction_set = match field("instruction_set")? {
+            "full" => InstructionSet::Full,
+            "day2" => InstructionSet::Day2,
+            value => return Err(invalid("instruction_set", value)),
+        };
+        let arithmetic = match field("arithmetic")? {
+            "wrapping" => Arithmetic::Wrapping,
+            "checked" => Arithmetic::Checked,
+            value => return Err(invalid("arithmetic", value)),
+        };
+        Ok(Snapshot {
+            memory,
+            instruction_pointer: parse("instruction_pointer", field("instruction_pointer")?)?,
+            relative_base: parse("relative_base", field("relative_base")?)?,
+            memory_limit,
+            instruction_set,
+            arithmetic,
+            steps: parse("steps", field("steps")?)?,
+            step_budget: parse_optional("step_budget")?,
+            time_limit: parse_optional("time_limit_ms")?.map(Duration::from_millis),
+            elapsed: Duration::from_millis(parse("elapsed_ms", field("elapsed_ms")?)?),
+            pending_inputs: parse_list("inputs")?,
+        })
+    }
+}
+
+impl<W: Word> TuringMachine<W> {
+    /// Capture the current state of the machine.
+    /// The tracer, the instruction cache, profiles, transcripts, undo logs and
+    /// watchpoints are not part of the snapshot.
+    pub fn snapshot(&self) -> Snapshot<W> {
+        Snapshot {
+            memory: self.memory_tape.clone(),
+            instruction_pointer: self.instruction_pointer,
+            relative_base: self.relative_base.clone(),
+            memory_limit: self.memory_limit,
+            instruction_set: self.instruction_set,
+            arithmetic: self.arithmetic,
+            steps: self.steps,
+            step_budget: self.step_budget,
+            time_limit: self.time_limit,
+            elapsed: self
+                .started_at
+                .map_or(Duration::from_secs(0), |started_at| started_at.elapsed()),
+            pending_inputs: self.inputs.iter().cloned().collect(),
+        }
+    }
+
+    /// Build a machine that resumes from `snapshot`.
+    pub fn restore(snapshot: Snapshot<W>) -> Self {
+        let mut machine = TuringMachine::from_words(snapshot.memory)
+            .with_memory_limit(snapshot.memory_limit)
+            .with_instruction_set(snapshot.instruction_set)
+            .with_arithmetic(snapshot.arithmetic);
+        machine.instruction_pointer = snapshot.instruction_pointer;
+        machine.relative_base = snapshot.relative_base;
+        machine.steps = snapshot.steps;
+        machine.step_budget = snapshot.step_budget;
+        machine.time_limit = snapshot.time_limit;
+        if snapshot.time_limit.is_some() {
+            machine.started_at = Instant::now().checked_sub(snapshot.elapsed);
+        }
+        machine.inputs = snapshot.pending_inputs.into_iter().collect();
+        machine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::snapshot::Snapshot;
+    use crate::test_programs::RUNNING_SUM;
+    use crate::{Arithmetic, MachineError, Status, TuringMachine};
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    #[test]
+    fn resume_from_a_saved_snapshot() {
+        let mut machine = TuringMachine::new(RUNNING_SUM.to_vec());
+        machine.push_input(3);
+        machine.push_input(4);
+        machine.push_input(5);
+        assert_eq!(machine.run().unwrap(), Status::Output(3));
+
+        let snapshot = machine.snapshot();
+        let text = snapshot.to_string();
+        assert_eq!(Snapshot::from_str(&text).unwrap(), snapshot);
+
+        let mut restored = TuringMachine::restore(Snapshot::from_str(&text).unwrap());
+        for machine in [&mut machine, &mut restored].iter_mut() {
+            assert_eq!(machine.run().unwrap(), Status::Output(7));
+            assert_eq!(machine.run().unwrap(), Status::Output(12));
+            assert_eq!(machine.run().unwrap(), Status::NeedsInput);
+        }
+        assert_eq!(machine.snapshot(), restored.snapshot());
+    }
+
+    #[test]
+    fn restore_the_configuration() {
+        let memory_tape = RUNNING_SUM.iter().map(|&w| i128::from(w)).collect();
+        let mut machine = TuringMachine::<i128>::from_words(memory_tape)
+            .with_step_budget(10)
+            .with_arithmetic(Arithmetic::Checked)
+            .with_time_limit(Duration::from_secs(60));
+        machine.push_input(3);
+        assert_eq!(machine.run().unwrap(), Status::Output(3));
+        assert_eq!(machine.steps(), 4);
+
+        let snapshot = machine.snapshot();
+        assert_eq!(snapshot.step_budget, Some(10));
+        assert_eq!(snapshot.arithmetic, Arithmetic::Checked);
+        assert_eq!(snapshot.time_limit, Some(Duration::from_secs(60)));
+        let text = snapshot.to_string();
+        let mut restored = TuringMachine::restore(Snapshot::<i128>::from_str(&text).unwrap());
+        assert_eq!(restored.steps(), 4);
+
+        // The restored machine only has what is left of the step budget.
+        for input in 1..=10 {
+            restored.push_input(input);
+        }
+        assert_eq!(
+            restored.execute(vec![]).unwrap_err(),
+            MachineError::BudgetExhausted { steps: 10, instruction_pointer: 4 }
+        );
+    }
+
+    #[test]
+    fn reject_unknown_versions() {
+        let error = Snapshot::<i64>::from_str("intcode-snapshot 3\nmemory 99").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid snapshot: unsupported version 3");
+    }
+}
@@ -0,0 +1,94 @@
+use crate::ParameterMode;
+use std::fmt;
+use std::io::{Stdout, Write};
+
+/// Something that happened while a `TuringMachine` was running a program.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Event<W = i64> {
+    /// The instruction at `address` has been decoded and is about to be executed.
+    Instruction {
+        address: usize,
+        opcode: u32,
+        parameter_modes: [ParameterMode; 3],
+    },
+    /// The parameter in `position` (1-based) has been resolved to `value`.
+    /// For parameters the instruction writes to, `value` is the target address.
+    Parameter { position: usize, value: W },
+    /// `value` has been stored at `address`.
+    Write { address: usize, value: W },
+    /// The program consumed an input.
+    Input(W),
+    /// The program produced an output.
+    Output(W),
+    /// The relative base has been moved to a new value.
+    RelativeBase(W),
+}
+
+impl<W: fmt::Display> fmt::Display for Event<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Instruction {
+                address,
+                opcode,
+                parameter_modes,
+            } => write!(
+                f,
+                "[{}] opcode {} with parameter modes {:?}",
+                address, opcode, parameter_modes
+            ),
+            Event::Parameter { position, value } => {
+                write!(f, "    parameter {}: {}", position, value)
+            }
+            Event::Write { address, value } => write!(f, "    memory[{}] <- {}", address, value),
+            Event::Input(value) => write!(f, "    input: {}", value),
+            Event::Output(value) => write!(f, "    output: {}", value),
+            Event::RelativeBase(value) => write!(f, "    relative base: {}", value),
+        }
+    }
+}
+
+/// A hook to observe what a `TuringMachine` is doing, one `Event` at a time.
+///
+/// Any `FnMut(Event)` closure is a `Tracer`.
+pub trait Tracer<W = i64>: Send {
+    fn trace(&mut self, event: Event<W>);
+}
+
+impl<W, F> Tracer<W> for F
+where
+    F: FnMut(Event<W>) + Send,
+{
+    fn trace(&mut self, event: Event<W>) {
+        self(event)
+    }
+}
+
+/// The default tracer: it ignores every event.
+pub struct NoopTracer;
+
+impl<W> Tracer<W> for NoopTracer {
+    fn trace(&mut self, _event: Event<W>) {}
+}
+
+/// A tracer writing a human-readable line for every event.
+pub struct PrintTracer<T> {
+    writer: T,
+}
+
+impl<T: Write + Send> PrintTracer<T> {
+    pub fn new(writer: T) -> Self {
+        Self { writer }
+    }
+}
+
+impl PrintTracer<Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+impl<T: Write + Send, W: fmt::Display> Tracer<W> for PrintTracer<T> {
+    fn trace(&mut self, event: Event<W>) {
+        writeln!(self.writer, "{}", event).expect("Failed to write trace");
+    }
+}
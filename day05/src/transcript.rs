@@ -0,0 +1,279 @@
+//! Record the inputs and outputs of a run, to reproduce it later.
+//!
+//! Transcripts are stored as plain text, one entry per line with the number of steps
+//! executed before the instruction that consumed or produced the value:
+//!
+//! ```text
+//! intcode-transcript 1
+//! in 0 5
+//! out 7 25
+//! ```
+use crate::format::{self, Format, ParseError};
+use crate::{MachineError, Outcome, TuringMachine, Word};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+const FORMAT: Format = Format {
+    name: "transcript",
+    header: "intcode-transcript",
+    version: 1,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry<W = i64> {
+    Input { step: u64, value: W },
+    Output { step: u64, value: W },
+}
+
+impl<W: fmt::Display> fmt::Display for Entry<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Input { step, value } => write!(f, "in {} {}", step, value),
+            Entry::Output { step, value } => write!(f, "out {} {}", step, value),
+        }
+    }
+}
+
+/// Every input consumed and every output produced by a program, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transcript<W = i64> {
+    pub entries: Vec<Entry<W>>,
+}
+
+impl<W: Word> Transcript<W> {
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        format::save(self, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        format::load(path)
+    }
+
+    /// The recorded inputs, in the order they were consumed.
+    pub fn inputs(&self) -> impl Iterator<Item = &W> {
+        self.entries.iter().filter_map(|entry| match entry {
+            Entry::Input { value, .. } => Some(value),
+            Entry::Output { .. } => None,
+        })
+    }
+}
+
+impl<W: fmt::Display> fmt::Display for Transcript<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        FORMAT.write_header(f)?;
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Word> FromStr for Transcript<W> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = FORMAT.check_header(s)?;
+        let mut entries = vec![];
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let invalid = || FORMAT.error(format!("invalid entry `{}`", line));
+            let (kind, step, value) = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [kind, step, value] => (*kind, *step, *value),
+                _ => return Err(invalid()),
+            };
+            let step = u64::from_str(step).map_err(|_| invalid())?;
+            let value = W::from_str(value).map_err(|_| invalid())?;
+            entries.push(match kind {
+                "in" => Entry::Input { step, value },
+                "out" => Entry::Output { step, value },
+                _ => return Err(invalid()),
+            });
+        }
+        Ok(Transcript { entries })
+    }
+}
+
+/// Where a replay stopped matching the transcript.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence<W = i64> {
+    /// The index of the first entry that does not match.
+    pub index: usize,
+    /// `None` if the replay produced more entries than the transcript.
+    pub expected: Option<Entry<W>>,
+    /// `None` if the replay stopped before producing the expected entry.
+    pub found: Option<Entry<W>>,
+}
+
+impl<W: fmt::Display> fmt::Display for Divergence<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |entry: &Option<Entry<W>>| match entry {
+            Some(entry) => format!("`{}`", entry),
+            None => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "The replay diverged at entry {}: expected {}, found {}",
+            self.index,
+            describe(&self.expected),
+            describe(&self.found)
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError<W = i64> {
+    Divergence(Divergence<W>),
+    /// The program failed before diverging from the transcript.
+    Machine(MachineError),
+}
+
+impl<W: fmt::Display> fmt::Display for ReplayError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Divergence(divergence) => write!(f, "{}", divergence),
+            ReplayError::Machine(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl<W: fmt::Debug + fmt::Display> std::error::Error for ReplayError<W> {}
+
+impl<W: Word> TuringMachine<W> {
+    /// Record every input the program consumes and every output it produces.
+    /// Retrieve them with `transcript`.
+    pub fn with_recording(mut self) -> Self {
+        self.transcript = Some(Transcript {
+            entries: Vec::new(),
+        });
+        self
+    }
+
+    /// `None` if recording is disabled.
+    pub fn transcript(&self) -> Option<&Transcript<W>> {
+        self.transcript.as_ref()
+    }
+
+    pub(crate) fn record(&mut self, entry: Entry<W>) {
+        if let Some(transcript) = &mut self.transcript {
+            transcript.entries.push(entry);
+        }
+    }
+
+    /// Run the program again, feeding it the inputs of `transcript`, and check that it
+    /// consumes and produces the same values at the same steps.
+    /// It stops at the first entry that does not match.
+    pub fn replay(self, transcript: &Transcript<W>) -> Result<(), ReplayError<W>> {
+        let mut machine = self.with_recording();
+        let mut inputs = transcript.inputs().cloned().collect::<Vec<_>>().into_iter();
+        let expected = &transcript.entries;
+        let diverges_at = |recorded: &[Entry<W>], from: usize| {
+            (from..recorded.len()).find(|&i| expected.get(i) != Some(&recorded[i]))
+        };
+        loop {
+            let checked = machine.transcript.as_ref().unwrap().entries.len();
+            let outcome = machine.step_with(&mut inputs);
+            let recorded = &machine.transcript.as_ref().unwrap().entries;
+            if let Some(index) = diverges_at(recorded, checked) {
+                return Err(ReplayError::Divergence(Divergence {
+                    index,
+                    expected: expected.get(index).cloned(),
+                    found: Some(recorded[index].clone()),
+                }));
+            }
+            match outcome.map_err(ReplayError::Machine)? {
+                Outcome::Success | Outcome::Output(_) => {}
+                Outcome::NeedsInput | Outcome::Halt => break,
+            }
+        }
+
+        let recorded = &machine.transcript.as_ref().unwrap().entries;
+        if recorded.len() < expected.len() {
+            return Err(ReplayError::Divergence(Divergence {
+                index: recorded.len(),
+                expected: Some(expected[recorded.len()].clone()),
+                found: None,
+            }));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_programs::RUNNING_SUM;
+    use crate::transcript::{Divergence, Entry, ReplayError, Transcript};
+    use crate::TuringMachine;
+    use std::str::FromStr;
+
+    #[test]
+    fn record_and_replay() {
+        let mut machine = TuringMachine::new(RUNNING_SUM.to_vec()).with_recording();
+        machine.run_with(&mut vec![3, 4].into_iter(), &mut vec![]).unwrap();
+        let transcript = machine.transcript().unwrap().clone();
+        let text = "\
+intcode-transcript 1
+in 1 3
+out 3 3
+in 5 4
+out 7 7
+";
+        assert_eq!(transcript.to_string(), text);
+        assert_eq!(Transcript::from_str(text).unwrap(), transcript);
+
+        TuringMachine::new(RUNNING_SUM.to_vec())
+            .replay(&transcript)
+            .unwrap();
+    }
+
+    #[test]
+    fn failed_inputs_are_not_recorded() {
+        // The input is written to a negative address.
+        let mut machine = TuringMachine::new(vec![3, -1, 99, 0]).with_recording();
+        machine.run_with(&mut vec![5].into_iter(), &mut vec![]).unwrap_err();
+        assert_eq!(machine.transcript().unwrap().entries, vec![]);
+
+        // The input was put back in the queue.
+        machine.store(1, 3).unwrap();
+        machine.run().unwrap();
+        assert_eq!(machine.memory()[3], 5);
+        let entries = vec![Entry::Input { step: 0, value: 5 }];
+        assert_eq!(machine.transcript().unwrap().entries, entries);
+    }
+
+    #[test]
+    fn replay_flags_the_first_divergence() {
+        let transcript = Transcript::from_str(
+            "intcode-transcript 1\nin 1 3\nout 3 3\nin 5 4\nout 7 8\nin 9 1\nout 11 9\n",
+        )
+        .unwrap();
+        let error = TuringMachine::new(RUNNING_SUM.to_vec())
+            .replay(&transcript)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ReplayError::Divergence(Divergence {
+                index: 3,
+                expected: Some(Entry::Output { step: 7, value: 8 }),
+                found: Some(Entry::Output { step: 7, value: 7 }),
+            })
+        );
+        assert_eq!(
+            error.to_string(),
+            "The replay diverged at entry 3: expected `out 7 8`, found `out 7 7`"
+        );
+
+        // The program does more than the transcript recorded.
+        let transcript = Transcript::from_str("intcode-transcript 1\nin 1 3\n").unwrap();
+        let error = TuringMachine::new(RUNNING_SUM.to_vec())
+            .replay(&transcript)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ReplayError::Divergence(Divergence {
+                index: 1,
+                expected: None,
+                found: Some(Entry::Output { step: 3, value: 3 }),
+            })
+        );
+    }
+}
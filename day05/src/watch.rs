@@ -0,0 +1,213 @@
+//! Find out which instructions read or write a memory cell.
+//!
+//! Reads are the memory accesses of parameters in position or relative mode: fetching
+//! instructions and their immediate parameters does not trigger watchpoints.
+use crate::{TuringMachine, Word};
+use std::ops::RangeInclusive;
+
+/// How many watchpoint hits a machine keeps until they are taken with `take_watch_hits`:
+/// beyond it, the oldest ones are dropped.
+pub const WATCH_HITS_CAPACITY: usize = 10_000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Watch the memory cells in `addresses`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub addresses: RangeInclusive<usize>,
+    pub reads: bool,
+    pub writes: bool,
+    /// Stop `run` and `run_with` after the instruction that triggered the watchpoint.
+    pub pause: bool,
+}
+
+impl Watchpoint {
+    pub fn reads(addresses: RangeInclusive<usize>) -> Self {
+        Self {
+            addresses,
+            reads: true,
+            writes: false,
+            pause: false,
+        }
+    }
+
+    pub fn writes(addresses: RangeInclusive<usize>) -> Self {
+        Self {
+            addresses,
+            reads: false,
+            writes: true,
+            pause: false,
+        }
+    }
+
+    /// Watch both reads and writes.
+    pub fn accesses(addresses: RangeInclusive<usize>) -> Self {
+        Self {
+            addresses,
+            reads: true,
+            writes: true,
+            pause: false,
+        }
+    }
+
+    pub fn pausing(mut self) -> Self {
+        self.pause = true;
+        self
+    }
+
+    fn matches(&self, address: usize, access: Access) -> bool {
+        let watched = match access {
+            Access::Read => self.reads,
+            Access::Write => self.writes,
+        };
+        watched && self.addresses.contains(&address)
+    }
+}
+
+/// A watchpoint has been triggered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Hit<W = i64> {
+    pub address: usize,
+    pub access: Access,
+    /// The value that has been read or written.
+    pub value: W,
+    /// The address of the instruction that accessed the cell.
+    pub instruction_pointer: usize,
+}
+
+impl<W: Word> TuringMachine<W> {
+    pub fn with_watchpoint(mut self, watchpoint: Watchpoint) -> Self {
+        self.watchpoints.push(watchpoint);
+        self
+    }
+
+    /// Add or remove watchpoints while the program is running.
+    pub fn watchpoints_mut(&mut self) -> &mut Vec<Watchpoint> {
+        &mut self.watchpoints
+    }
+
+    /// Every watchpoint hit since the last call, in order, up to the last
+    /// `WATCH_HITS_CAPACITY` of them.
+    pub fn take_watch_hits(&mut self) -> Vec<Hit<W>> {
+        std::mem::take(&mut self.watch_hits).into()
+    }
+
+    pub(crate) fn check_watchpoints(&mut self, address: usize, access: Access, value: &W) {
+        let mut pause = false;
+        let mut hit = false;
+        for watchpoint in self.watchpoints.iter().filter(|w| w.matches(address, access)) {
+            hit = true;
+            pause |= watchpoint.pause;
+        }
+        if !hit {
+            return;
+        }
+        let hit = Hit {
+            address,
+            access,
+            value: value.clone(),
+            instruction_pointer: self.instruction_pointer,
+        };
+        self.pending_hits.push((hit, pause));
+    }
+
+    /// The instruction has been executed: its hits are reported.
+    pub(crate) fn commit_watch_hits(&mut self) {
+        for (hit, pause) in self.pending_hits.drain(..) {
+            if pause && self.paused_on.is_none() {
+                self.paused_on = Some(hit.clone());
+            }
+            if self.watch_hits.len() == WATCH_HITS_CAPACITY {
+                self.watch_hits.pop_front();
+            }
+            self.watch_hits.push_back(hit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::watch::{Access, Hit, Watchpoint, WATCH_HITS_CAPACITY};
+    use crate::{MachineError, Status, TuringMachine};
+
+    #[test]
+    fn find_who_corrupts_a_cell() {
+        // Store 10 at address 20, read it back and output it, then overwrite it with 11.
+        let memory_tape = vec![1101, 4, 6, 20, 4, 20, 1101, 5, 6, 20, 99];
+        let mut machine = TuringMachine::new(memory_tape)
+            .with_watchpoint(Watchpoint::reads(20..=20))
+            .with_watchpoint(Watchpoint::writes(15..=25).pausing());
+
+        let paused = Hit {
+            address: 20,
+            access: Access::Write,
+            value: 10,
+            instruction_pointer: 0,
+        };
+        assert_eq!(machine.run().unwrap(), Status::Watchpoint(paused));
+        assert_eq!(machine.run().unwrap(), Status::Output(10));
+        let paused = Hit {
+            address: 20,
+            access: Access::Write,
+            value: 11,
+            instruction_pointer: 6,
+        };
+        assert_eq!(machine.run().unwrap(), Status::Watchpoint(paused));
+        assert_eq!(machine.run().unwrap(), Status::Halted);
+
+        let hits: Vec<_> = machine
+            .take_watch_hits()
+            .into_iter()
+            .map(|hit| (hit.access, hit.instruction_pointer))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![(Access::Write, 0), (Access::Read, 4), (Access::Write, 6)]
+        );
+        assert!(machine.take_watch_hits().is_empty());
+    }
+
+    #[test]
+    fn execute_does_not_stop_at_watchpoints() {
+        let memory_tape = vec![1101, 4, 6, 20, 4, 20, 1101, 5, 6, 20, 99];
+        let machine = TuringMachine::new(memory_tape).with_watchpoint(Watchpoint::accesses(20..=20).pausing());
+        let (memory_tape, output_tape) = machine.execute(vec![]).unwrap();
+        assert_eq!(output_tape, vec![10]);
+        assert_eq!(memory_tape[20], 11);
+    }
+
+    #[test]
+    fn only_the_last_hits_are_kept() {
+        // Increment a counter, forever.
+        let memory_tape = vec![101, 1, 8, 8, 1105, 1, 0, 99, 0];
+        let mut machine = TuringMachine::new(memory_tape)
+            .with_watchpoint(Watchpoint::reads(8..=8))
+            .with_step_budget(3 * WATCH_HITS_CAPACITY as u64);
+        machine.run().unwrap_err();
+
+        let hits = machine.take_watch_hits();
+        assert_eq!(hits.len(), WATCH_HITS_CAPACITY);
+        assert_eq!(hits.last().unwrap().value, 3 * WATCH_HITS_CAPACITY as i64 / 2 - 1);
+    }
+
+    #[test]
+    fn failed_instructions_do_not_trigger_watchpoints() {
+        // Read the watched cell 5 and write beyond the memory limit.
+        let memory_tape = vec![1, 5, 5, 2000, 99, 0];
+        let mut machine = TuringMachine::new(memory_tape)
+            .with_memory_limit(1000)
+            .with_watchpoint(Watchpoint::reads(5..=5).pausing());
+        assert_eq!(
+            machine.run().unwrap_err(),
+            MachineError::OutOfMemory { address: 2000, memory_limit: 1000 }
+        );
+        assert!(machine.take_watch_hits().is_empty());
+
+        machine.set_instruction_pointer(4);
+        assert_eq!(machine.run().unwrap(), Status::Halted);
+    }
+}
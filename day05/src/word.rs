@@ -0,0 +1,98 @@
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// The type of the values a `TuringMachine` stores in memory and computes with.
+///
+/// It is implemented for `i32`, `i64`, `i128` and for the arbitrary-precision `BigInt`.
+pub trait Word:
+    Clone
+    + PartialEq
+    + PartialOrd
+    + fmt::Debug
+    + fmt::Display
+    + FromStr
+    + From<i32>
+    + Send
+    + 'static
+{
+    /// `None` if the word does not fit in an `i64`.
+    fn to_i64(&self) -> Option<i64>;
+
+    /// `None` if the word does not fit in an `i128`.
+    fn to_i128(&self) -> Option<i128>;
+
+    /// `None` on overflow.
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+
+    /// `None` on overflow.
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+
+    fn wrapping_add(&self, rhs: &Self) -> Self;
+
+    fn wrapping_mul(&self, rhs: &Self) -> Self;
+}
+
+macro_rules! primitive_word {
+    ($($t:ty),*) => {
+        $(
+            impl Word for $t {
+                fn to_i64(&self) -> Option<i64> {
+                    i64::try_from(*self).ok()
+                }
+
+                fn to_i128(&self) -> Option<i128> {
+                    Some(i128::from(*self))
+                }
+
+                fn checked_add(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_add(*self, *rhs)
+                }
+
+                fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_mul(*self, *rhs)
+                }
+
+                fn wrapping_add(&self, rhs: &Self) -> Self {
+                    <$t>::wrapping_add(*self, *rhs)
+                }
+
+                fn wrapping_mul(&self, rhs: &Self) -> Self {
+                    <$t>::wrapping_mul(*self, *rhs)
+                }
+            }
+        )*
+    };
+}
+
+primitive_word!(i32, i64, i128);
+
+impl Word for BigInt {
+    fn to_i64(&self) -> Option<i64> {
+        ToPrimitive::to_i64(self)
+    }
+
+    fn to_i128(&self) -> Option<i128> {
+        ToPrimitive::to_i128(self)
+    }
+
+    // Big integers never overflow.
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+
+    fn wrapping_add(&self, rhs: &Self) -> Self {
+        self + rhs
+    }
+
+    fn wrapping_mul(&self, rhs: &Self) -> Self {
+        self * rhs
+    }
+}
@@ -24,7 +24,7 @@ fn read_input(path: &str) -> Vec<(String, String)> {
 fn count_orbits<'a>(graph: &DiGraphMap<&'a str, ()>, root_node: &'a str) -> usize {
     let mut acc = 0;
     for neighbour in graph.neighbors_directed(root_node, Direction::Outgoing) {
-        acc += _count_orbits(&graph, &neighbour, 1);
+        acc += _count_orbits(graph, neighbour, 1);
     }
     acc
 }
@@ -32,7 +32,7 @@ fn count_orbits<'a>(graph: &DiGraphMap<&'a str, ()>, root_node: &'a str) -> usiz
 fn _count_orbits<'a>(graph: &DiGraphMap<&'a str, ()>, node: &'a str, depth: usize) -> usize {
     let mut acc = depth;
     for neighbour in graph.neighbors_directed(node, Direction::Outgoing) {
-        acc += _count_orbits(&graph, &neighbour, depth + 1);
+        acc += _count_orbits(graph, neighbour, depth + 1);
     }
     acc
 }
@@ -54,7 +54,7 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use crate::{count_orbits, parse};
+    use crate::count_orbits;
     use petgraph::graphmap::DiGraphMap;
 
     #[test]
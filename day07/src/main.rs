@@ -1,77 +1,78 @@
 use std::str::FromStr;
+use day05::network::Network;
 use day05::TuringMachine;
 use itertools::Itertools;
+use rayon::prelude::*;
+use threaded::threaded_loop_amplifiers;
 
-fn read_input(path: &str) -> Vec<i32> {
+mod threaded;
+
+fn read_input(path: &str) -> Vec<i64> {
     let input = std::fs::read_to_string(path).expect("Failed to read input");
     let instructions = input
         .trim()
         .split(",")
-        .map(|s| i32::from_str(&s).expect("Failed to parse instruction"))
+        .map(|s| i64::from_str(s).expect("Failed to parse instruction"))
         .collect();
     instructions
 }
 
-fn amplifiers(settings: impl Iterator<Item=u8>, memory_tape: Vec<i32>) -> i32 {
-    let mut input_signal = 0;
+fn amplifiers(settings: Vec<u8>, memory_tape: Vec<i64>) -> i64 {
+    let mut network = Network::new();
+    let mut previous = None;
     for setting in settings {
-        let program = TuringMachine::new(memory_tape.clone());
-        let (_, output_tape) = program.execute(vec![setting as i32, input_signal]);
-        input_signal = output_tape[0]
+        let amplifier = network.add_machine(TuringMachine::new(memory_tape.clone()));
+        network.seed(amplifier, setting as i64);
+        match previous {
+            Some(previous) => network.connect(previous, amplifier),
+            None => network.seed(amplifier, 0),
+        };
+        previous = Some(amplifier);
     }
-    input_signal
+    let last = previous.expect("There must be at least one amplifier");
+    network.collect(last);
+    network.run().expect("The amplifier program failed");
+    network.outputs(last)[0]
 }
 
-fn loop_amplifiers(settings: Vec<u8>, memory_tape: Vec<i32>) -> i32 {
-    let mut input_signal = 0;
-    let mut memory_tapes = vec![
-        memory_tape.clone(),
-        memory_tape.clone(),
-        memory_tape.clone(),
-        memory_tape.clone(),
-        memory_tape.clone(),
-    ];
-    for (amplifier_index, setting) in settings.into_iter().enumerate().cycle() {
-        println!("Amplifier index: {:?}", amplifier_index);
-        let memory_tape = &memory_tapes[amplifier_index];
-        let program = TuringMachine::new(memory_tape.to_vec());
-        let (memory_tape, output_tape) = program.execute(vec![setting as i32, input_signal]);
-        memory_tapes[amplifier_index] = memory_tape;
-        if output_tape.len() == 0 {
-            break;
-        }
-        input_signal = output_tape[0]
-    }
-    input_signal
+fn loop_amplifiers(settings: Vec<u8>, memory_tape: Vec<i64>) -> i64 {
+    let settings: Vec<i64> = settings.into_iter().map(i64::from).collect();
+    threaded_loop_amplifiers(&settings, &memory_tape).expect("The amplifier loop failed")
 }
 
+/// Try every sequence of `n_amplifiers` distinct phase settings drawn from `phases`,
+/// in parallel, and return the one that sends the highest signal to the thrusters
+/// together with the signal.
+/// Ties are broken in favour of the lexicographically smallest sequence.
+fn optimal_settings(
+    phases: impl IntoIterator<Item=u8>,
+    n_amplifiers: usize,
+    memory_tape: &[i64],
+    thrust: fn(Vec<u8>, Vec<i64>) -> i64,
+) -> Option<(Vec<u8>, i64)> {
+    let candidates: Vec<Vec<u8>> = phases.into_iter().permutations(n_amplifiers).collect();
+    candidates
+        .into_par_iter()
+        .map(|settings| {
+            let signal = thrust(settings.clone(), memory_tape.to_vec());
+            (settings, signal)
+        })
+        .max_by(|(a, a_signal), (b, b_signal)| a_signal.cmp(b_signal).then_with(|| b.cmp(a)))
+}
 
 fn main() {
     let memory_tape = read_input("input.txt");
 
-    let mut thrusters_outputs: Vec<i32> = Vec::new();
-    let mut looped_thrusters_outputs: Vec<i32> = Vec::new();
-    for settings in (0..=4).permutations(5) {
-        let thrust = amplifiers(settings.clone().into_iter(), memory_tape.clone());
-        thrusters_outputs.push(thrust);
-    }
-
-    let optimal_thrust = thrusters_outputs.into_iter().max().unwrap();
-    println!("Maximum signal: {:?}", optimal_thrust);
+    let (settings, thrust) = optimal_settings(0..=4, 5, &memory_tape, amplifiers).unwrap();
+    println!("Maximum signal: {:?} with settings {:?}", thrust, settings);
 
-    let mut thrusters_outputs: Vec<i32> = Vec::new();
-    for settings in (5..=9).permutations(5) {
-        let thrust = loop_amplifiers(settings.clone(), memory_tape.clone());
-        thrusters_outputs.push(thrust);
-    }
-
-    let optimal_thrust = thrusters_outputs.into_iter().max().unwrap();
-    println!("Maximum looped signal: {:?}", optimal_thrust);
+    let (settings, thrust) = optimal_settings(5..=9, 5, &memory_tape, loop_amplifiers).unwrap();
+    println!("Maximum looped signal: {:?} with settings {:?}", thrust, settings);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::loop_amplifiers;
+    use crate::{amplifiers, loop_amplifiers, optimal_settings};
 
     #[test]
     fn loop_amplifiers_test() {
@@ -82,4 +83,24 @@ mod tests {
         let thrust = loop_amplifiers(vec![9,8,7,6,5], memory_tape.clone());
         assert_eq!(thrust, 139629729);
     }
+
+    #[test]
+    fn optimal_settings_test() {
+        let memory_tape = vec![
+            3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0
+        ];
+        let optimum = optimal_settings(0..=4, 5, &memory_tape, amplifiers);
+        assert_eq!(optimum, Some((vec![4,3,2,1,0], 43210)));
+
+        let memory_tape = vec![
+            3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,
+            27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5
+        ];
+        let optimum = optimal_settings(5..=9, 5, &memory_tape, loop_amplifiers);
+        assert_eq!(optimum, Some((vec![9,8,7,6,5], 139629729)));
+
+        // Fewer amplifiers than phases.
+        let optimum = optimal_settings(0..=4, 2, &[3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0], amplifiers);
+        assert_eq!(optimum, Some((vec![4,3], 43)));
+    }
 }
@@ -0,0 +1,264 @@
+//! Amplifiers wired in a feedback loop, each one running on its own thread.
+//!
+//! Signals travel between amplifiers over channels. A shared `Monitor` keeps track
+//! of which amplifiers are blocked waiting for a signal and of how many signals are
+//! still in flight towards each of them: when every amplifier that has not halted
+//! is blocked and no signal is on its way, nobody can make progress anymore and
+//! the loop is deadlocked.
+use day05::{InputSource, MachineError, OutputSink, Status, TuringMachine};
+use std::fmt;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FeedbackError {
+    /// Every running amplifier is waiting for a signal that will never come.
+    Deadlock,
+    /// An amplifier program failed.
+    Machine { amplifier: usize, error: MachineError },
+}
+
+impl fmt::Display for FeedbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedbackError::Deadlock => write!(f, "All amplifiers are waiting for a signal"),
+            FeedbackError::Machine { amplifier, error } => {
+                write!(f, "Amplifier {} failed: {}", amplifier, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FeedbackError {}
+
+enum Message {
+    Signal(i64),
+    /// The loop is deadlocked: stop waiting.
+    Shutdown,
+}
+
+struct AmplifierState {
+    halted: bool,
+    waiting: bool,
+    /// Signals that have been sent to the amplifier but it has not received yet.
+    in_flight: usize,
+}
+
+struct Monitor {
+    amplifiers: Mutex<Vec<AmplifierState>>,
+    /// One sender per amplifier, to wake them up on deadlock.
+    senders: Vec<Sender<Message>>,
+}
+
+impl Monitor {
+    /// Record that a signal is about to be sent to `amplifier`.
+    /// It returns `false` if the amplifier has halted and the signal should be dropped.
+    fn sending(&self, amplifier: usize) -> bool {
+        let mut amplifiers = self.amplifiers.lock().unwrap();
+        let state = &mut amplifiers[amplifier];
+        if state.halted {
+            return false;
+        }
+        state.in_flight += 1;
+        true
+    }
+
+    fn received(&self, amplifier: usize) {
+        let mut amplifiers = self.amplifiers.lock().unwrap();
+        let state = &mut amplifiers[amplifier];
+        state.in_flight -= 1;
+        state.waiting = false;
+    }
+
+    /// Record that `amplifier` is about to block waiting for a signal.
+    /// It returns `true` if doing so would deadlock the loop.
+    fn waiting(&self, amplifier: usize) -> bool {
+        let mut amplifiers = self.amplifiers.lock().unwrap();
+        amplifiers[amplifier].waiting = true;
+        self.check_deadlock(&amplifiers)
+    }
+
+    fn halted(&self, amplifier: usize) {
+        let mut amplifiers = self.amplifiers.lock().unwrap();
+        amplifiers[amplifier].halted = true;
+        self.check_deadlock(&amplifiers);
+    }
+
+    fn check_deadlock(&self, amplifiers: &[AmplifierState]) -> bool {
+        let all_halted = amplifiers.iter().all(|a| a.halted);
+        let deadlock = !all_halted
+            && amplifiers
+                .iter()
+                .all(|a| a.halted || (a.waiting && a.in_flight == 0));
+        if deadlock {
+            for sender in &self.senders {
+                // The amplifier may have already exited.
+                let _ = sender.send(Message::Shutdown);
+            }
+        }
+        deadlock
+    }
+}
+
+struct SignalInput {
+    amplifier: usize,
+    receiver: Receiver<Message>,
+    monitor: Arc<Monitor>,
+    deadlocked: bool,
+}
+
+impl InputSource for SignalInput {
+    fn next_input(&mut self) -> Option<i64> {
+        if self.deadlocked {
+            return None;
+        }
+        let message = match self.receiver.try_recv() {
+            Ok(message) => message,
+            Err(_) => {
+                if self.monitor.waiting(self.amplifier) {
+                    self.deadlocked = true;
+                    return None;
+                }
+                self.receiver.recv().ok()?
+            }
+        };
+        match message {
+            Message::Signal(signal) => {
+                self.monitor.received(self.amplifier);
+                Some(signal)
+            }
+            Message::Shutdown => {
+                self.deadlocked = true;
+                None
+            }
+        }
+    }
+}
+
+struct SignalOutput {
+    next_amplifier: usize,
+    sender: Sender<Message>,
+    monitor: Arc<Monitor>,
+    last_signal: Option<i64>,
+}
+
+impl OutputSink for SignalOutput {
+    fn emit(&mut self, signal: i64) {
+        self.last_signal = Some(signal);
+        if self.monitor.sending(self.next_amplifier) {
+            // The next amplifier cannot have dropped its receiver: it has not halted yet.
+            let _ = self.sender.send(Message::Signal(signal));
+        }
+    }
+}
+
+/// Run one amplifier per phase setting, each on its own thread, feeding the output of
+/// every amplifier into the next one and the output of the last one back into the first.
+/// It returns the last signal emitted by the last amplifier.
+pub fn threaded_loop_amplifiers(
+    settings: &[i64],
+    memory_tape: &[i64],
+) -> Result<i64, FeedbackError> {
+    let n_amplifiers = settings.len();
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..n_amplifiers).map(|_| channel()).unzip();
+    let monitor = Arc::new(Monitor {
+        amplifiers: Mutex::new(
+            (0..n_amplifiers)
+                .map(|_| AmplifierState {
+                    halted: false,
+                    waiting: false,
+                    in_flight: 0,
+                })
+                .collect(),
+        ),
+        senders: senders.clone(),
+    });
+
+    // Phase settings first, then the initial signal for the first amplifier.
+    for (amplifier, &setting) in settings.iter().enumerate() {
+        monitor.sending(amplifier);
+        senders[amplifier].send(Message::Signal(setting)).unwrap();
+    }
+    monitor.sending(0);
+    senders[0].send(Message::Signal(0)).unwrap();
+
+    let handles: Vec<_> = receivers
+        .into_iter()
+        .enumerate()
+        .map(|(amplifier, receiver)| {
+            let next_amplifier = (amplifier + 1) % n_amplifiers;
+            let mut input = SignalInput {
+                amplifier,
+                receiver,
+                monitor: monitor.clone(),
+                deadlocked: false,
+            };
+            let mut output = SignalOutput {
+                next_amplifier,
+                sender: senders[next_amplifier].clone(),
+                monitor: monitor.clone(),
+                last_signal: None,
+            };
+            let mut machine = TuringMachine::new(memory_tape.to_vec());
+            thread::spawn(move || {
+                let result = machine.run_with(&mut input, &mut output);
+                input.monitor.halted(amplifier);
+                match result {
+                    Ok(Status::Halted) => Ok(output.last_signal),
+                    Ok(_) => Err(FeedbackError::Deadlock),
+                    Err(error) => Err(FeedbackError::Machine { amplifier, error }),
+                }
+            })
+        })
+        .collect();
+
+    let mut results: Vec<_> = handles
+        .into_iter()
+        .map(|handle| handle.join().expect("An amplifier thread panicked"))
+        .collect();
+    // A failing amplifier leaves the others waiting: report the root cause, not the deadlock.
+    if let Some(position) = results
+        .iter()
+        .position(|r| matches!(r, Err(FeedbackError::Machine { .. })))
+    {
+        return Err(results.swap_remove(position).unwrap_err());
+    }
+    match results.pop() {
+        Some(Ok(Some(signal))) => Ok(signal),
+        Some(Err(e)) => Err(e),
+        // The last amplifier never emitted anything.
+        _ => Err(FeedbackError::Deadlock),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::threaded::{threaded_loop_amplifiers, FeedbackError};
+    use day05::MachineError;
+
+    #[test]
+    fn every_amplifier_waiting_is_a_deadlock() {
+        // Read three inputs without ever producing an output: after its phase setting
+        // and the initial signal the first amplifier waits, like all the others.
+        let memory_tape = vec![3, 7, 3, 7, 3, 7, 99, 0];
+        let result = threaded_loop_amplifiers(&[1, 2, 3], &memory_tape);
+        assert_eq!(result, Err(FeedbackError::Deadlock));
+    }
+
+    #[test]
+    fn failing_amplifiers_are_reported() {
+        let memory_tape = vec![3, 5, 3, 5, 77, 0];
+        let result = threaded_loop_amplifiers(&[0, 1], &memory_tape);
+        assert_eq!(
+            result,
+            Err(FeedbackError::Machine {
+                amplifier: 0,
+                error: MachineError::UnknownOpcode {
+                    opcode: 77,
+                    address: 4
+                }
+            })
+        );
+    }
+}
@@ -1,4 +1,3 @@
-use std::alloc::handle_alloc_error;
 use std::str::FromStr;
 use ndarray::{Array2, Array3, Axis, ArrayView2};
 
@@ -20,7 +19,7 @@ fn get_layers(width: usize, height: usize, digits: Vec<u8>) -> Array3<u8> {
 fn checksum(layers: &Array3<u8>) -> u32 {
     let layer = layers
         .axis_iter(Axis(0))
-        .min_by_key(|l| count_digit(&l, 0))
+        .min_by_key(|l| count_digit(l, 0))
         .expect("Failed to find maximum layer");
     count_digit(&layer, 1) * count_digit(&layer, 2)
 }
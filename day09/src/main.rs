@@ -1,3 +1,49 @@
+use std::str::FromStr;
+use day05::TuringMachine;
+
+fn read_input(path: &str) -> Vec<i64> {
+    let input = std::fs::read_to_string(path).expect("Failed to read input");
+    let instructions = input
+        .trim()
+        .split(",")
+        .map(|s| i64::from_str(s).expect("Failed to parse instruction"))
+        .collect();
+    instructions
+}
+
 fn main() {
-    println!("Hello, world!");
+    let memory_tape = read_input("input.txt");
+
+    // First part
+    let program = TuringMachine::new(memory_tape.clone());
+    let (_, output_tape) = program.execute(vec![1]).expect("The program failed");
+    println!("BOOST keycode: {:?}", output_tape);
+
+    // Second part
+    let program = TuringMachine::new(memory_tape.clone());
+    let (_, output_tape) = program.execute(vec![2]).expect("The program failed");
+    println!("Distress signal coordinates: {:?}", output_tape);
+}
+
+#[cfg(test)]
+mod tests {
+    use day05::TuringMachine;
+
+    #[test]
+    fn quine_test() {
+        let memory_tape = vec![
+            109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99
+        ];
+        let program = TuringMachine::new(memory_tape.clone());
+        let (_, output_tape) = program.execute(vec![]).unwrap();
+        assert_eq!(output_tape, memory_tape);
+    }
+
+    #[test]
+    fn large_number_test() {
+        let memory_tape = vec![104,1125899906842624,99];
+        let program = TuringMachine::new(memory_tape);
+        let (_, output_tape) = program.execute(vec![]).unwrap();
+        assert_eq!(output_tape, vec![1125899906842624]);
+    }
 }